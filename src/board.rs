@@ -1,2 +1,21 @@
+pub mod animation;
+pub mod archive;
+#[allow(clippy::module_inception)]
 pub mod board;
+pub mod cache;
+pub mod choices;
+pub mod connector;
+pub mod editor;
+pub mod extract;
+pub mod fetch;
+pub mod geometry;
+pub mod history;
+pub mod layer;
+pub mod merge;
+pub mod schema;
+pub mod shape;
+pub mod sniff;
+pub mod spatial;
 pub mod store;
+pub mod style;
+pub mod vector;
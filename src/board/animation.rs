@@ -0,0 +1,141 @@
+use std::io::Cursor;
+use std::time::Duration;
+
+use ggez::graphics::{Image, ImageFormat};
+use ggez::Context;
+use image::codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder};
+use image::{AnimationDecoder, Frames, ImageResult};
+
+/// frames shown for less than this are slowed down to it, like browsers do
+const MIN_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+/// the frames of an animated image (gif, apng or webp) and how far along it is
+#[derive(Clone)]
+pub struct Animation {
+    frames: Vec<Image>,
+    /// how long each frame is shown for
+    delays: Vec<Duration>,
+    current: usize,
+    /// time spent on the current frame so far
+    elapsed: Duration,
+}
+
+impl std::fmt::Debug for Animation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Animation({} frames)", self.frames.len())
+    }
+}
+
+impl Animation {
+    /// the animation in the encoded image `bytes`, [`None`] if it isnt animated
+    pub fn decode(ctx: &Context, bytes: &[u8]) -> ImageResult<Option<Self>> {
+        let frames = if bytes.starts_with(b"GIF8") {
+            GifDecoder::new(Cursor::new(bytes))?.into_frames()
+        } else if bytes.starts_with(b"\x89PNG") {
+            let decoder = PngDecoder::new(Cursor::new(bytes))?;
+            if !decoder.is_apng() {
+                return Ok(None);
+            }
+            decoder.apng().into_frames()
+        } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+            let decoder = WebPDecoder::new(Cursor::new(bytes))?;
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+            decoder.into_frames()
+        } else {
+            return Ok(None);
+        };
+
+        Self::collect(ctx, frames)
+    }
+
+    fn collect(ctx: &Context, frames: Frames) -> ImageResult<Option<Self>> {
+        let mut animation = Self {
+            frames: Vec::new(),
+            delays: Vec::new(),
+            current: 0,
+            elapsed: Duration::ZERO,
+        };
+        for frame in frames {
+            let frame = frame?;
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            animation.delays.push(delay(numer, denom));
+
+            let buffer = frame.into_buffer();
+            animation.frames.push(Image::from_pixels(
+                ctx,
+                buffer.as_raw(),
+                ImageFormat::Rgba8UnormSrgb,
+                buffer.width(),
+                buffer.height(),
+            ));
+        }
+
+        Ok((animation.frames.len() > 1).then_some(animation))
+    }
+
+    #[inline]
+    pub fn frame(&self) -> &Image {
+        &self.frames[self.current]
+    }
+
+    /// moves the animation along by `dt`, returns whether the frame changed
+    pub fn advance(&mut self, dt: Duration) -> bool {
+        let (current, elapsed) = step(&self.delays, self.current, self.elapsed + dt);
+        let changed = current != self.current;
+        (self.current, self.elapsed) = (current, elapsed);
+        changed
+    }
+}
+
+/// a frame delay of `numer / denom` milliseconds, with tiny (or missing) delays slowed down
+fn delay(numer: u32, denom: u32) -> Duration {
+    let delay = Duration::from_micros(numer as u64 * 1000 / denom.max(1) as u64);
+    if delay < MIN_DELAY {
+        DEFAULT_DELAY
+    } else {
+        delay
+    }
+}
+
+/// the frame and time spent on it after `elapsed` has been spent on frame `current`, looping around
+fn step(delays: &[Duration], mut current: usize, mut elapsed: Duration) -> (usize, Duration) {
+    // a long pause (like while the window was hidden) shouldnt take ages to catch up on
+    let total = delays.iter().sum::<Duration>();
+    if elapsed > total {
+        elapsed = Duration::from_nanos((elapsed.as_nanos() % total.as_nanos()) as u64);
+    }
+
+    while elapsed >= delays[current] {
+        elapsed -= delays[current];
+        current = (current + 1) % delays.len();
+    }
+    (current, elapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{delay, step};
+
+    #[test]
+    fn frames_follow_their_delays() {
+        let ms = Duration::from_millis;
+        let delays = [ms(100), ms(50), ms(200)];
+
+        assert_eq!(step(&delays, 0, ms(99)), (0, ms(99)));
+        assert_eq!(step(&delays, 0, ms(100)), (1, ms(0)));
+        assert_eq!(step(&delays, 0, ms(170)), (2, ms(20)));
+        // loops back to the start
+        assert_eq!(step(&delays, 2, ms(210)), (0, ms(10)));
+        // a whole loop and a bit
+        assert_eq!(step(&delays, 0, ms(360)), (0, ms(10)));
+
+        assert_eq!(delay(0, 1), ms(100));
+        assert_eq!(delay(10, 1), ms(100));
+        assert_eq!(delay(70, 1), ms(70));
+    }
+}
@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+use super::board::{Board, ImageType, Item};
+use super::schema::{self, Header};
+use super::store;
+
+/// version of the archive layout this build writes
+pub const VERSION: u32 = 1;
+
+/// files of the board directory which go into the archive as they are
+const FILES: [&str; 3] = ["store.store", "layers.store", "app_state.store"];
+const MANIFEST: &str = "manifest.json";
+/// directory in the archive with the cached images
+const CACHE: &str = "cache";
+
+/// describes whats in an archive
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    /// version of the store inside
+    pub store_version: u32,
+    /// cached images inside, by blob (or name for ones cached before blobs)
+    pub images: Vec<String>,
+}
+
+/// packs the (saved) board in `root` into a single zip file at `to`
+/// `images` being the names of the cached images the board uses
+pub fn export(root: &Path, images: &[&str], to: &Path) -> Result<Manifest, Box<dyn Error>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default();
+
+    for file in FILES {
+        match std::fs::read(root.join(file)) {
+            Ok(contents) => {
+                zip.start_file(file, options)?;
+                zip.write_all(&contents)?;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let mut manifest = Manifest {
+        version: VERSION,
+        store_version: schema::VERSION,
+        images: Vec::new(),
+    };
+    for &name in images {
+        match std::fs::read(root.join(".cache").join(name)) {
+            Ok(contents) => {
+                zip.start_file(format!("{CACHE}/{name}"), options)?;
+                zip.write_all(&contents)?;
+                manifest.images.push(name.to_owned());
+            }
+            Err(e) => println!("couldnt add the cached image {name} to the archive: {e}"),
+        }
+    }
+
+    zip.start_file(MANIFEST, options)?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+
+    store::write_atomic(to, &zip.finish()?.into_inner())?;
+    Ok(manifest)
+}
+
+/// unpacks an archive into a new board at `root`, which has to be empty (or not exist yet)
+/// local images are pointed at the boards own cache since the paths they came from are on another machine
+pub fn import(archive: &Path, root: &Path) -> Result<Manifest, Box<dyn Error>> {
+    if std::fs::read_dir(root).is_ok_and(|mut d| d.next().is_some()) {
+        return Err(format!("{} already has a board in it", root.display()).into());
+    }
+
+    let mut zip = ZipArchive::new(std::fs::File::open(archive)?)?;
+    let manifest = serde_json::from_reader::<_, Manifest>(zip.by_name(MANIFEST)?)?;
+    if manifest.version > VERSION {
+        return Err(format!(
+            "the archive is version {} but only up to {VERSION} can be read",
+            manifest.version
+        )
+        .into());
+    }
+
+    let cache = root.join(".cache");
+    std::fs::create_dir_all(&cache)?;
+    let cache = cache.canonicalize()?;
+
+    for file in FILES {
+        match zip.by_name(file) {
+            Ok(mut entry) => {
+                let mut contents = Vec::new();
+                entry.read_to_end(&mut contents)?;
+                std::fs::write(root.join(file), contents)?;
+            }
+            Err(zip::result::ZipError::FileNotFound) => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    for name in &manifest.images {
+        // names come from elsewhere so they cant be allowed outside the cache
+        if Path::new(name).file_name() != Some(name.as_ref()) {
+            return Err(format!("image name {name} isnt just a file name").into());
+        }
+
+        let mut contents = Vec::new();
+        zip.by_name(&format!("{CACHE}/{name}"))?
+            .read_to_end(&mut contents)?;
+        std::fs::write(cache.join(name), contents)?;
+    }
+
+    let store = root.join("store.store");
+    if let Ok(contents) = std::fs::read_to_string(&store) {
+        std::fs::write(&store, relink(&contents, &cache)?)?;
+    }
+
+    Ok(manifest)
+}
+
+/// points every local image in the store `contents` at `cache`, upgrading the store on the way
+/// lines which cant be read are left for the store to quarantine
+fn relink(contents: &str, cache: &Path) -> Result<String, Box<dyn Error>> {
+    let (version, lines) = schema::split_header(contents)?;
+
+    let mut relinked = serde_json::to_string(&Header::current())? + "\n";
+    for line in lines {
+        match schema::migrate(line, version) {
+            Ok(mut item) => {
+                if let Item::Image(image) = &mut item {
+                    if let ImageType::Local(path) = &image.kind {
+                        let name = if image.blob.is_empty() {
+                            Board::name_from_path(path)
+                        } else {
+                            &image.blob
+                        };
+                        image.kind = ImageType::Local(cache.join(name).display().to_string());
+                    }
+                }
+                relinked += &serde_json::to_string(&item)?;
+            }
+            Err(_) => relinked += line,
+        }
+        relinked.push('\n');
+    }
+
+    Ok(relinked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export, import};
+
+    #[test]
+    fn boards_survive_a_round_trip() {
+        let dir = std::env::temp_dir().join(format!("board-archive-test-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        let (from, to) = (dir.join("from"), dir.join("to"));
+        std::fs::create_dir_all(from.join(".cache")).unwrap();
+
+        let image = r#"{"Image":{"id":1,"layer":0,"position":[0.0,0.0],"scale":[1.0,1.0],"rotation":0.0,"kind":{"Local":"/home/someone/cat.png"}}}"#;
+        std::fs::write(
+            from.join("store.store"),
+            format!("{{\"version\":1}}\n{image}\nbroken\n"),
+        )
+        .unwrap();
+        std::fs::write(from.join(".cache").join("cat.png"), "meow").unwrap();
+
+        let archive = dir.join("board.zip");
+        let manifest = export(&from, &["cat.png", "missing.png"], &archive).unwrap();
+        assert_eq!(manifest.images, ["cat.png"]);
+        assert_eq!(import(&archive, &to).unwrap(), manifest);
+
+        let cache = to.join(".cache").canonicalize().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(cache.join("cat.png")).unwrap(),
+            "meow"
+        );
+        let store = std::fs::read_to_string(to.join("store.store")).unwrap();
+        assert!(store.contains(&cache.join("cat.png").display().to_string()));
+        assert!(store.ends_with("broken\n"));
+
+        // boards arent imported over each other
+        assert!(import(&archive, &to).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
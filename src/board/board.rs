@@ -1,19 +1,41 @@
-use std::io::Write;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::time::Instant;
 
 use crate::camera::Camera;
 
-use super::store::Store;
+use super::animation::Animation;
+use super::archive;
+use super::cache::{self, Cache};
+use super::choices::Choices;
+use super::connector::ItemConnector;
+use super::editor::TextEditor;
+use super::extract;
+use super::fetch::{Fetcher, Purpose};
+use super::geometry::OrientedRect;
+use super::history::{Edit, History};
+use super::layer::{LayerId, Layers};
+use super::merge;
+use super::shape::{self, ItemPen, ItemShape, ShapeKind, Tool};
+use super::sniff::{self, Content};
+use super::spatial::SpatialIndex;
+use super::store::{self, Store};
+use super::style::{FontFamily, ShapeStyle, TextStyle};
+use super::vector::Vector;
 use ggez::{
     event::MouseButton,
     graphics::{self, Canvas, Color, DrawParam, Image, Rect, Text},
     input::keyboard::KeyCode,
     Context,
 };
-use reqwest;
 use serde::{Deserialize, Serialize};
 
 // position is in pixels
 
+/// identifies an item for as long as it exists (even across saves)
+/// 0 is for items which havent been given one yet
+pub type ItemId = u64;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum ImageType {
     Web(String),    // url (from a web page)
@@ -22,12 +44,45 @@ pub enum ImageType {
     Local(String), // path
 }
 
+/// an item as its put on the clipboard
+pub enum Copied {
+    Text(String),
+    /// png
+    Image(Vec<u8>),
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ItemImage {
-    /// path to cached item
-    /// only optional because of serialisation
+    /// stays the same for as long as the item exists, unlike its index
+    #[serde(default)]
+    pub id: ItemId,
+    /// the layer the item is on
+    #[serde(default)]
+    pub layer: LayerId,
+    /// [`None`] while the image is being downloaded (or if that failed)
     #[serde(skip)]
     handle: Option<Image>,
+    /// why downloading the image failed
+    #[serde(skip)]
+    pub failed: Option<String>,
+    /// the frames if its animated, the first of which is the handle
+    #[serde(skip)]
+    animation: Option<Box<Animation>>,
+    /// the svg if its one, rasterised at the zoom its shown at
+    #[serde(skip)]
+    vector: Option<Box<Vector>>,
+    /// size of the full image if its big enough to have thumbnails, which may be shown in its place
+    #[serde(skip)]
+    full_size: Option<(u32, u32)>,
+    /// longest side of the thumbnail being shown (or being loaded), [`None`] for the full image
+    #[serde(skip)]
+    tier: Option<u32>,
+    /// whether its animation is stopped
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub paused: bool,
+    /// hash of the cached image, empty for items from before the cache was content addressed
+    #[serde(default)]
+    pub blob: String,
     pub position: (f32, f32),
     pub scale: (f32, f32),
     pub rotation: f32,
@@ -37,7 +92,17 @@ pub struct ItemImage {
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ItemText {
+    /// stays the same for as long as the item exists, unlike its index
+    #[serde(default)]
+    pub id: ItemId,
+    /// the layer the item is on
+    #[serde(default)]
+    pub layer: LayerId,
     text: String,
+    pub style: TextStyle,
+    /// while being typed into the markdown markers are shown
+    #[serde(skip)]
+    editing: bool,
 
     position: (f32, f32),
     scale: f32,
@@ -48,23 +113,104 @@ pub struct ItemText {
 pub enum Item {
     Image(ItemImage),
     Text(ItemText),
+    Shape(ItemShape),
+    Pen(ItemPen),
+    Connector(ItemConnector),
+}
+
+/// where an item is and how its oriented
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Transform {
+    pub position: (f32, f32),
+    /// for [`Item::Text`] only the `scale.0` is used
+    /// for [`Item::Shape`] its the size in world units
+    pub scale: (f32, f32),
+    pub rotation: f32,
 }
 
 #[derive(Clone, Copy)]
 pub enum Selectable {
-    /// for item management
+    /// for item management (the whole selection is managed along with the pressed item)
     Item(usize),
     /// for camera movements
     Board,
+    /// for selecting every item that intersects the box between the last press and the mouse
+    Marquee,
+    /// for the shape or pen stroke being drawn with a [`Tool`] (always the last item)
+    Drawing,
+}
+
+/// where to move items within their layer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Depth {
+    /// over the next item above
+    Forward,
+    /// under the next item below
+    Backward,
+    Front,
+    Back,
+}
+
+/// new order of items on `layers` (one per item) after moving the `selected` ones, the item at `order[i]` going to `i`
+/// forward and backward only ever pass items on their own layer, front and back pass every item
+/// but since items are drawn layer by layer that only shows within their layer
+fn depth_order(layers: &[LayerId], selected: &[bool], to: Depth) -> Vec<usize> {
+    let mut order = (0..layers.len()).collect::<Vec<usize>>();
+
+    match to {
+        // selected items keep their order between themselves
+        Depth::Front => order.sort_by_key(|&i| selected[i]),
+        Depth::Back => order.sort_by_key(|&i| !selected[i]),
+
+        // swaps with the next unselected item on the same layer, going from the furthest along first
+        // so neighbouring selected items move together
+        Depth::Forward | Depth::Backward => {
+            let mut positions = (0..order.len()).collect::<Vec<usize>>();
+            if to == Depth::Forward {
+                positions.reverse();
+            }
+
+            for p in positions {
+                let i = order[p];
+                if !selected[i] {
+                    continue;
+                }
+
+                let next = |q: &usize| !selected[order[*q]] && layers[order[*q]] == layers[i];
+                let other = if to == Depth::Forward {
+                    (p + 1..order.len()).find(next)
+                } else {
+                    (0..p).rev().find(next)
+                };
+                if let Some(q) = other {
+                    order.swap(p, q);
+                }
+            }
+        }
+    }
+
+    order
+}
+
+/// the selected items being dragged around
+struct Drag {
+    /// (index, transform) of each selected item when the drag started
+    before: Vec<(usize, Transform)>,
+    /// in world coords, what the selection is scaled and rotated around
+    centre: (f32, f32),
 }
 
 struct BoardState {
     /// in world coords
     last_press: (f32, f32),
-    /// index of selected item in items array
+    /// what the mouse is currently managing
     selected: Option<Selectable>,
+    /// indices of the selected items in items array
+    selection: Vec<usize>,
     /// (background, text) colours
     colours: (Color, Color),
+    drag: Option<Drag>,
+    tool: Tool,
 }
 
 impl BoardState {
@@ -72,7 +218,10 @@ impl BoardState {
         Self {
             last_press: (0.0, 0.0),
             selected: None,
+            selection: Vec::new(),
             colours: (crate::LIGHT, crate::DARK),
+            drag: None,
+            tool: Tool::default(),
         }
     }
 
@@ -84,29 +233,43 @@ impl BoardState {
 pub struct Board {
     store: Store,
     items: Vec<Item>,
-    // TODO: prob should just use `Image` instead of `ItemImage`
-    choices: Vec<(Option<ItemImage>, String)>,
+    choices: Choices,
+    history: History,
+    index: SpatialIndex,
+    /// the text item being typed into
+    editor: Option<TextEditor>,
+    /// the last id given to an item
+    next_id: ItemId,
+    fetcher: Fetcher,
+    layers: Layers,
+    /// items as they were last saved or loaded, to merge in changes made elsewhere
+    saved: HashMap<ItemId, String>,
+    /// ids of the animated images so still ones arent looked at every frame
+    /// removed ones are kept in case theyre brought back
+    animated: HashSet<ItemId>,
+    /// ids of the big images showing more than their smallest thumbnail,
+    /// so they can be brought back down once theyre away from the screen
+    upgraded: HashSet<ItemId>,
+    /// when the first and latest unsaved changes were made, [`None`] if everything is saved
+    unsaved: Option<(Instant, Instant)>,
 
     pub camera: Camera,
     state: BoardState,
 }
 
 impl ImageType {
-    #[inline]
+    /// urls which dont look like an image are [`ImageType::Web`] until its known whats behind them
     /// returns empty argument if no matches
     pub fn type_from_argument(argument: &str) -> Self {
-        if argument.starts_with("http") {
-            if argument.ends_with(".png")
-                || argument.ends_with(".jpg")
-                || argument.ends_with(".jpeg")
-                || argument.ends_with(".gif")
-            {
+        let argument = argument.trim();
+        if argument.starts_with("http://") || argument.starts_with("https://") {
+            if sniff::looks_like_image(argument) {
                 ImageType::Online(argument.to_owned())
             } else {
                 ImageType::Web(argument.to_owned())
             }
-        } else if argument.starts_with("/") {
-            ImageType::Local(argument.to_owned())
+        } else if let Some(path) = sniff::local_path(argument) {
+            ImageType::Local(path.display().to_string())
         } else {
             ImageType::Local("".to_owned())
         }
@@ -115,56 +278,763 @@ impl ImageType {
     #[inline]
     pub fn argument(&self) -> &str {
         match self {
-            ImageType::Web(url) => &url,
-            ImageType::Online(url) => &url,
-            ImageType::Local(path) => &path,
+            ImageType::Web(url) => url,
+            ImageType::Online(url) => url,
+            ImageType::Local(path) => path,
         }
     }
 }
 
 impl Board {
-    const CHOICE_AMOUNT: usize = 4;
-
+    /// `keep_backups` being how many older versions of the store are kept around
     pub fn create<P: AsRef<std::path::Path>>(
         store_path: P,
+        keep_backups: usize,
         ctx: &mut Context,
     ) -> std::io::Result<Self> {
-        let store_path = store_path.as_ref();
-        let store = Store::create(store_path)?;
-        let contents: Vec<String> = std::fs::read_to_string(&store_path.join("store.store"))?
-            .lines()
-            .map(String::from)
-            .collect();
-
-        let mut items: Vec<Item> = Vec::<Item>::with_capacity(contents.len());
-        for line in contents.iter() {
-            items.push(match store.read_line(line, ctx) {
-                Ok(i) => i,
-                Err(e) => {
-                    println!("line {line} couldnt be read: {e}");
-                    continue;
-                }
-            })
-        }
+        let mut store = Store::create(store_path, keep_backups)?;
+        let contents = store.recover()?;
+        let mut items = store.read_items(&contents, ctx)?;
 
-        ctx.gfx.add_font(
-            "fancy font",
-            graphics::FontData::from_path(ctx, "/fonts/MeowScript-Regular.ttf").unwrap(),
-        );
+        FontFamily::add_fonts(ctx);
+
+        let layers = Layers::read(&store.layers).unwrap_or_else(|e| {
+            println!("couldnt read the layers, starting with just the base layer: {e}");
+            Layers::default()
+        });
+
+        let mut next_id = 0;
+        Self::assign_ids(&mut items, &mut next_id);
+        let saved = merge::snapshot(&items);
+        store.cache.recount(Self::blobs(&items));
+        let animated = Self::animated(&items);
 
-        Ok(Self {
+        let mut board = Self {
             store,
             items,
-            choices: Vec::default(),
+            choices: Choices::default(),
+            history: History::default(),
+            index: SpatialIndex::new(),
+            editor: None,
+            next_id,
+            fetcher: Fetcher::new(),
+            layers,
+            saved,
+            unsaved: None,
+            animated,
+            upgraded: HashSet::new(),
 
             state: BoardState::new(),
-            camera: Camera::new(&ctx),
-        })
+            camera: Camera::new(ctx),
+        };
+        board.request_missing();
+
+        Ok(board)
     }
 
     #[inline]
     pub fn add_text(&mut self, text: String) {
-        self.items.push(Item::Text(ItemText::new(text)));
+        self.push(Item::Text(ItemText::new(text)));
+    }
+
+    /// adds an item ontop of the others on the current layer (giving it an id), recording it in the history
+    fn push(&mut self, mut item: Item) {
+        self.adopt(&mut item);
+        self.items.push(item);
+        self.index.inserted(self.items.len() - 1);
+        self.record(Edit::Remove(self.items.len() - 1));
+    }
+
+    /// gives a new item an id and puts it on the current layer
+    fn adopt(&mut self, item: &mut Item) {
+        if let Item::Image(i) = &item {
+            self.store.cache.retain(&i.blob);
+        }
+        item.set_id(self.new_id());
+        item.set_layer(self.layers.current().id);
+        self.track(item);
+    }
+
+    /// keeps track of the item if its an animated image
+    fn track(&mut self, item: &Item) {
+        if let Item::Image(i) = item {
+            if i.animated() {
+                self.animated.insert(i.id);
+            }
+        }
+    }
+
+    /// moves the animated images along by `dt`, leaving ones which are paused or on hidden layers
+    /// and, if `offscreen` is false, ones which arent on screen
+    pub fn animate(&mut self, dt: std::time::Duration, offscreen: bool) {
+        let view = self.camera.viewport();
+
+        for &id in &self.animated {
+            let Some(i) = self.index.position(id) else {
+                continue;
+            };
+            let Item::Image(image) = &mut self.items[i] else {
+                continue;
+            };
+            if image.paused || !self.layers.visible(image.layer) {
+                continue;
+            }
+            if !offscreen && !self.index.rect(i).intersects(view) {
+                continue;
+            }
+            if let Some(animation) = &mut image.animation {
+                animation.advance(dt);
+            }
+        }
+    }
+
+    /// rasterises the svgs on screen again if the zoom has changed enough that theyd look blurry or wastefully big,
+    /// and swaps big images to the thumbnail tier that suits how big they are on screen
+    /// images away from the screen only keep their smallest thumbnail, so memory stays bounded on big boards
+    /// the thumbnails are made and decoded in the background, only being uploaded here once theyre ready
+    pub fn refresh_resolutions(&mut self, ctx: &Context) {
+        for blob in self.store.cache.take_fresh() {
+            self.fetcher.make_thumbnails(&self.store.cache.dir, &blob);
+        }
+        for (id, blob, tier, pixels) in self.fetcher.poll_decoded() {
+            let Some(Item::Image(image)) = self.index.position(id).map(|i| &mut self.items[i])
+            else {
+                continue;
+            };
+            // relinked or wanting another tier since
+            if image.blob != blob || image.tier != tier {
+                continue;
+            }
+            match pixels {
+                Ok(pixels) => {
+                    image.handle = Some(Image::from_pixels(
+                        ctx,
+                        pixels.as_raw(),
+                        graphics::ImageFormat::Rgba8UnormSrgb,
+                        pixels.width(),
+                        pixels.height(),
+                    ))
+                }
+                // the tier is kept so its not tried again every frame
+                Err(e) => println!("couldnt load {} at {tier:?}: {e}", image.kind.argument()),
+            }
+        }
+
+        let view = self.camera.viewport();
+        for i in self.index.query(view) {
+            let Item::Image(image) = &mut self.items[i] else {
+                continue;
+            };
+            if !self.layers.visible(image.layer) {
+                continue;
+            }
+            let scale = image.scale.0.abs().max(image.scale.1.abs()) * self.camera.zoom;
+            if let Some(vector) = &mut image.vector {
+                vector.refresh(ctx, scale);
+            }
+        }
+
+        // with a margin so images are sharp by the time theyre scrolled to
+        let near = (
+            view.0 - view.2 / 2.0,
+            view.1 - view.3 / 2.0,
+            view.2 * 2.0,
+            view.3 * 2.0,
+        );
+        let mut seen = HashSet::new();
+        for i in self.index.query(near) {
+            let Item::Image(image) = &mut self.items[i] else {
+                continue;
+            };
+            let Some((width, height)) = image.full_size else {
+                continue;
+            };
+            if !self.layers.visible(image.layer) {
+                continue;
+            }
+
+            let side = width.max(height);
+            let scale = image.scale.0.abs().max(image.scale.1.abs()) * self.camera.zoom;
+            let tier = cache::tier(side as f32 * scale, side);
+            image.request_tier(&self.fetcher, &self.store.cache, tier);
+            if tier == Some(cache::TIERS[0]) {
+                self.upgraded.remove(&image.id);
+            } else {
+                self.upgraded.insert(image.id);
+            }
+            seen.insert(image.id);
+        }
+
+        let away = self
+            .upgraded
+            .iter()
+            .filter(|id| !seen.contains(id))
+            .copied()
+            .collect::<Vec<ItemId>>();
+        for id in away {
+            // removed ones are kept in case theyre brought back
+            let Some(i) = self.index.position(id) else {
+                continue;
+            };
+            self.upgraded.remove(&id);
+            if let Item::Image(image) = &mut self.items[i] {
+                image.request_tier(&self.fetcher, &self.store.cache, Some(cache::TIERS[0]));
+            }
+        }
+    }
+
+    /// pauses or plays the selected animated images, returns how many there were
+    pub fn toggle_playing_selection(&mut self) -> usize {
+        let mut toggled = 0;
+        for &i in &self.state.selection {
+            if let Item::Image(image) = &mut self.items[i] {
+                if image.animated() {
+                    image.paused = !image.paused;
+                    toggled += 1;
+                }
+            }
+        }
+
+        if toggled != 0 {
+            self.changed();
+        }
+        toggled
+    }
+
+    /// adds an image from raw image data (like a copied screenshot), keeping it in the cache as a local file
+    pub fn add_image_data(
+        &mut self,
+        bytes: &[u8],
+        ctx: &Context,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if sniff::sniff(bytes, None) != Content::Image {
+            return Err("its not an image".into());
+        }
+
+        // the file it came from is the one in the cache
+        let path = std::path::absolute(self.store.cache.path(&Cache::hash(bytes)))?;
+        let source = path.display().to_string();
+        let blob = self.store.cache.insert(&source, bytes)?;
+        let image = ItemImage::from_blob(&self.store, blob, &source, ctx)?;
+        self.push(Item::Image(image));
+
+        Ok(())
+    }
+
+    /// what copying the first selected item puts on the clipboard: its text or its image as png
+    /// [`None`] if theres nothing to copy
+    pub fn copy_selection(&self) -> Result<Option<Copied>, Box<dyn std::error::Error>> {
+        let Some(item) = self.state.selection.first().map(|&i| &self.items[i]) else {
+            return Ok(None);
+        };
+
+        match item {
+            Item::Image(i) if i.handle().is_some() => {
+                if let Some(vector) = &i.vector {
+                    return Ok(Some(Copied::Image(vector.to_png()?)));
+                }
+                let bytes = std::fs::read(self.store.cache.path(&i.blob))?;
+                if bytes.starts_with(b"\x89PNG") {
+                    return Ok(Some(Copied::Image(bytes)));
+                }
+
+                let mut png = std::io::Cursor::new(Vec::new());
+                image::load_from_memory(&bytes)?.write_to(&mut png, image::ImageFormat::Png)?;
+                Ok(Some(Copied::Image(png.into_inner())))
+            }
+            Item::Image(_) => Err("the image hasnt loaded".into()),
+            item => Ok(item.contents().map(|c| Copied::Text(c.to_owned()))),
+        }
+    }
+
+    /// adds an item for each dropped file as one step, laid out in a grid from the screen position `pos`
+    /// images become image items and other files their text (or the image theyre a link to)
+    /// gives back why each file which couldnt be added wasnt
+    pub fn add_dropped(
+        &mut self,
+        paths: &[std::path::PathBuf],
+        pos: (f32, f32),
+        ctx: &Context,
+    ) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut items = Vec::new();
+        for path in paths {
+            match self.item_from_file(path, ctx) {
+                Ok(item) => items.push(item),
+                Err(e) => errors.push(format!("couldnt add {}: {e}", path.display())),
+            }
+        }
+
+        let sizes = items
+            .iter()
+            .map(|i| {
+                let (_, _, w, h) = i.oriented(ctx).bounds();
+                (w, h)
+            })
+            .collect::<Vec<(f32, f32)>>();
+        let corners = grid(&sizes, self.camera.screen_to_world(pos));
+
+        let mut inverse = Vec::new();
+        for (mut item, corner) in items.into_iter().zip(corners) {
+            self.adopt(&mut item);
+            let item = item.with_position(corner);
+            inverse.push(Edit::Insert(self.items.len(), item).apply(&mut self.items));
+        }
+        if !inverse.is_empty() {
+            inverse.reverse();
+            let inverse = Edit::Group(inverse);
+            self.index.applied(&inverse);
+            self.record(inverse);
+            self.request_missing();
+        }
+
+        errors
+    }
+
+    /// an item for the file at `path`, read as text if it isnt an image
+    fn item_from_file(
+        &mut self,
+        path: &std::path::Path,
+        ctx: &Context,
+    ) -> Result<Item, Box<dyn std::error::Error>> {
+        let bytes = std::fs::read(path)?;
+        if sniff::sniff(&bytes, None) == Content::Image {
+            let source = path.display().to_string();
+            let blob = self.store.cache.insert(&source, &bytes)?;
+            return Ok(Item::Image(ItemImage::from_blob(
+                &self.store,
+                blob,
+                &source,
+                ctx,
+            )?));
+        }
+
+        let text = String::from_utf8(bytes).map_err(|_| "its neither an image nor text")?;
+        // links dragged out of a browser are saved as shortcuts with just the address in them
+        let link = text.trim();
+        let link = link
+            .strip_prefix("[InternetShortcut]")
+            .map_or(link, |l| l.trim().trim_start_matches("URL="));
+        if !link.contains(char::is_whitespace) && sniff::looks_like_image(link) {
+            return Ok(Item::Image(ItemImage::placeholder(link)));
+        }
+
+        Ok(Item::Text(ItemText::new(text)))
+    }
+
+    /// ids of the animated images in `items`
+    fn animated(items: &[Item]) -> HashSet<ItemId> {
+        items
+            .iter()
+            .filter(|i| matches!(i, Item::Image(x) if x.animated()))
+            .map(Item::id)
+            .collect()
+    }
+
+    /// the cached images used by `items`
+    fn blobs(items: &[Item]) -> impl Iterator<Item = &str> {
+        items.iter().filter_map(|i| match i {
+            Item::Image(i) => Some(i.blob.as_str()),
+            _ => None,
+        })
+    }
+
+    /// gives items from before ids existed one, `next_id` being the last id given out
+    fn assign_ids(items: &mut [Item], next_id: &mut ItemId) {
+        *next_id = items.iter().map(Item::id).fold(*next_id, ItemId::max);
+        for item in items.iter_mut().filter(|i| i.id() == 0) {
+            *next_id += 1;
+            item.set_id(*next_id);
+        }
+    }
+
+    #[inline]
+    fn new_id(&mut self) -> ItemId {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// brings the index up to date and reroutes the connectors if anything moved
+    fn refresh(&mut self, c: &Context) {
+        if !self.index.refresh(&self.items, c) || self.index.connectors().is_empty() {
+            return;
+        }
+
+        for id in self.index.connectors().to_vec() {
+            let Some(i) = self.index.position(id) else {
+                continue;
+            };
+            let Item::Connector(x) = &mut self.items[i] else {
+                continue;
+            };
+
+            let ends = (self.index.position(x.from), self.index.position(x.to));
+            let route = match ends {
+                (Some(a), Some(b)) => x.routing.route(self.index.rect(a), self.index.rect(b)),
+                _ => Vec::new(),
+            };
+            if route != x.route {
+                x.route = route;
+                self.index.moved(i);
+            }
+        }
+        self.index.refresh(&self.items, c);
+    }
+
+    /// connects the two selected items (from the first selected to the second)
+    /// returns whether they were connected
+    pub fn connect_selection(&mut self) -> bool {
+        let [a, b] = self.state.selection[..] else {
+            return false;
+        };
+        if [a, b]
+            .iter()
+            .any(|&i| matches!(self.items[i], Item::Connector(_)))
+        {
+            return false;
+        }
+
+        self.finish_drag();
+        let connector = ItemConnector::new(self.items[a].id(), self.items[b].id());
+        self.push(Item::Connector(connector));
+        self.state.selection = vec![self.items.len() - 1];
+        true
+    }
+
+    /// changes how the selected connectors are routed as one step
+    /// returns how many were changed
+    pub fn reroute_selection(&mut self) -> usize {
+        self.finish_drag();
+
+        let inverse = self
+            .state
+            .selection
+            .iter()
+            .filter_map(|&i| match &mut self.items[i] {
+                Item::Connector(x) => {
+                    let old = x.routing;
+                    x.routing = old.next();
+                    Some(Edit::Routing(i, old))
+                }
+                _ => None,
+            })
+            .collect::<Vec<Edit>>();
+
+        let changed = inverse.len();
+        if changed != 0 {
+            // routes only get worked out again when something moves
+            let inverse = Edit::Group(inverse);
+            self.index.applied(&inverse);
+            self.record(inverse);
+        }
+        changed
+    }
+
+    /// moves the selected items within their layers as one step
+    /// returns whether anything moved
+    pub fn reorder_selection(&mut self, to: Depth) -> bool {
+        self.finish_drag();
+
+        let selected = {
+            let mut selected = vec![false; self.items.len()];
+            self.state
+                .selection
+                .iter()
+                .for_each(|&i| selected[i] = true);
+            selected
+        };
+        let order = depth_order(
+            &self.items.iter().map(Item::layer).collect::<Vec<LayerId>>(),
+            &selected,
+            to,
+        );
+        if order.iter().enumerate().all(|(to, &from)| to == from) {
+            return false;
+        }
+
+        let Edit::Reorder(inverse) = Edit::Reorder(order).apply(&mut self.items) else {
+            unreachable!()
+        };
+        self.state.selection = self.state.selection.iter().map(|&i| inverse[i]).collect();
+        self.index.reordered(&inverse);
+        self.record(Edit::Reorder(inverse));
+        true
+    }
+
+    /// moves the selected items onto the current layer as one step
+    /// returns how many were moved
+    pub fn move_selection_to_layer(&mut self) -> usize {
+        self.finish_drag();
+        let layer = self.layers.current().id;
+
+        let mut inverse = Vec::new();
+        for &i in self.state.selection.iter() {
+            if self.items[i].layer() != layer {
+                inverse.push(Edit::Layer(i, layer).apply(&mut self.items));
+            }
+        }
+
+        let moved = inverse.len();
+        if moved != 0 {
+            self.record(Edit::Group(inverse));
+        }
+        moved
+    }
+
+    pub fn layers(&self) -> &Layers {
+        &self.layers
+    }
+
+    /// changes the layers with `f`, deselecting anything that can no longer be selected
+    pub fn change_layers<T>(&mut self, f: impl FnOnce(&mut Layers) -> T) -> T {
+        self.finish_drag();
+        let result = f(&mut self.layers);
+        self.changed();
+
+        let (items, layers) = (&self.items, &self.layers);
+        self.state
+            .selection
+            .retain(|&i| layers.selectable(items[i].layer()));
+        result
+    }
+
+    /// the current layer in the top left when theres more than one
+    pub fn draw_layers(&self, c: &mut Canvas) {
+        if self.layers.len() < 2 {
+            return;
+        }
+
+        c.draw(
+            Text::new(format!("layer: {}", self.layers.current()))
+                .set_scale(24.0)
+                .set_font("sans"),
+            DrawParam::new()
+                .dest([10.0, 10.0])
+                .color(self.state.colours.1),
+        );
+    }
+
+    /// records a change made to `items` which `inverse` reverses
+    fn record(&mut self, inverse: Edit) {
+        let lost = self.history.record(inverse);
+        self.forget(lost);
+        self.changed();
+    }
+
+    /// marks the board as having unsaved changes
+    fn changed(&mut self) {
+        let now = Instant::now();
+        self.unsaved = Some((self.unsaved.map_or(now, |(first, _)| first), now));
+    }
+
+    /// when the first and latest unsaved changes were made, [`None`] if everything is saved
+    #[inline]
+    pub fn unsaved(&self) -> Option<(Instant, Instant)> {
+        self.unsaved
+    }
+
+    /// reloads the store if something else changed it, merging in any unsaved changes
+    /// waits while anything is being typed, dragged or drawn, returns whether it reloaded
+    pub fn sync(&mut self, c: &Context) -> std::io::Result<bool> {
+        if !self.store.changed_elsewhere()
+            || self.editor.is_some()
+            || self.state.drag.is_some()
+            || self.state.selected.is_some()
+        {
+            return Ok(false);
+        }
+
+        let contents = self.store.recover()?;
+        let mut external = self.store.read_items(&contents, c)?;
+        Self::assign_ids(&mut external, &mut self.next_id);
+        let saved = merge::snapshot(&external);
+        let local = std::mem::take(&mut self.items);
+        self.items = merge::merge(&self.saved, local, external, &mut self.next_id);
+        self.animated = Self::animated(&self.items);
+        self.saved = saved;
+
+        if self.unsaved.is_none() {
+            match Layers::read(&self.store.layers) {
+                Ok(layers) => self.layers = layers,
+                Err(e) => println!("couldnt reread the layers: {e}"),
+            }
+        }
+
+        // indices in the history and selection dont line up anymore
+        // (anything only the history used is left for garbage collection)
+        self.history.clear();
+        self.store.cache.recount(Self::blobs(&self.items));
+        self.request_missing();
+        self.state.selection.clear();
+        // drawing reads the index before the next refresh, so it has to match the merged items now
+        self.index.invalidate();
+        self.refresh(c);
+        Ok(true)
+    }
+
+    /// lets go of the cached images of items which are gone for good
+    fn forget(&mut self, items: Vec<Item>) {
+        for item in items {
+            if let Item::Image(i) = item {
+                self.fetcher.cancel(|p| *p == Purpose::Item(i.id));
+                if let Err(e) = self.store.cache.release(&i.blob) {
+                    println!("Error removing cached image \"{}\": {e}", i.blob);
+                }
+            }
+        }
+    }
+
+    /// removes the item at `i` without recording a step, since bringing it back wouldnt make sense
+    /// whatever is selected, dragged or being typed into is kept
+    fn discard(&mut self, i: usize) {
+        let item = self.items.remove(i);
+        self.history.removed(i);
+        self.index.removed(i);
+
+        let shift = |j: usize| if j > i { j - 1 } else { j };
+        self.state.selection.retain(|&j| j != i);
+        self.state.selection.iter_mut().for_each(|j| *j = shift(*j));
+        self.state.selected = match self.state.selected {
+            Some(Selectable::Item(j)) if j == i => None,
+            Some(Selectable::Item(j)) => Some(Selectable::Item(shift(j))),
+            selected => selected,
+        };
+        if let Some(drag) = &mut self.state.drag {
+            drag.before.retain(|&(j, _)| j != i);
+            drag.before.iter_mut().for_each(|(j, _)| *j = shift(*j));
+        }
+        if let Some(editor) = &mut self.editor {
+            editor.removed(i);
+        }
+
+        self.forget(vec![item]);
+    }
+
+    /// starts typing into the item at `i` if its text (or a connectors label)
+    pub fn start_editing(&mut self, i: usize) -> bool {
+        let Some(contents) = self.items.get(i).and_then(Item::contents) else {
+            return false;
+        };
+
+        self.editor = Some(TextEditor::new(i, contents.to_owned()));
+        self.items[i].set_editing(true);
+        self.index.moved(i);
+        // the item shouldnt follow the mouse while typing
+        self.finish_drag();
+        self.state.selected = None;
+        self.state.selection = vec![i];
+        true
+    }
+
+    pub fn editor(&self) -> Option<&TextEditor> {
+        self.editor.as_ref()
+    }
+
+    /// changes the edit buffer with `f`, updating the item being typed into
+    pub fn edit_text(&mut self, f: impl FnOnce(&mut TextEditor)) {
+        let Some(editor) = &mut self.editor else {
+            return;
+        };
+
+        f(editor);
+        self.items[editor.item()].set_contents(editor.text().to_owned());
+        self.index.moved(editor.item());
+    }
+
+    /// stops typing, recording the change as one step
+    /// text items are removed if theyre left empty
+    pub fn finish_editing(&mut self) {
+        let Some(editor) = self.editor.take() else {
+            return;
+        };
+        let (i, original, text) = editor.finish();
+        self.items[i].set_editing(false);
+        self.index.moved(i);
+
+        if text.trim().is_empty() && matches!(self.items[i], Item::Text(_)) {
+            self.items[i].set_contents(original);
+            self.state.selection = vec![i];
+            self.remove_selection();
+        } else if text != original {
+            self.record(Edit::Text(i, original));
+        }
+    }
+
+    /// changes the style of every selected text item with `text` and every shape and pen stroke with `shape` as one step
+    /// returns how many were changed
+    pub fn restyle_selection(
+        &mut self,
+        text: impl Fn(&mut TextStyle),
+        shape: impl Fn(&mut ShapeStyle),
+    ) -> usize {
+        self.finish_drag();
+
+        /// applies `f` to `style`, giving back the old style if it changed
+        fn restyle<S: Clone + PartialEq>(style: &mut S, f: impl Fn(&mut S)) -> Option<S> {
+            let before = style.clone();
+            f(style);
+            (*style != before).then_some(before)
+        }
+
+        let mut inverse = Vec::new();
+        for &i in &self.state.selection {
+            let edit = match &mut self.items[i] {
+                Item::Text(item) => restyle(&mut item.style, &text).map(|s| Edit::Style(i, s)),
+                Item::Shape(ItemShape { style, .. })
+                | Item::Pen(ItemPen { style, .. })
+                | Item::Connector(ItemConnector { style, .. }) => {
+                    restyle(style, &shape).map(|s| Edit::ShapeStyle(i, s))
+                }
+                Item::Image(_) => None,
+            };
+
+            if let Some(edit) = edit {
+                inverse.push(edit);
+                self.index.moved(i);
+            }
+        }
+
+        let changed = inverse.len();
+        if changed != 0 {
+            self.record(Edit::Group(inverse));
+        }
+        changed
+    }
+
+    /// returns whether there was anything to undo
+    pub fn undo(&mut self) -> bool {
+        self.finish_drag();
+        self.finish_drawing();
+        self.state.selected = None;
+        self.state.selection.clear();
+        let Some(inverse) = self.history.undo(&mut self.items) else {
+            return false;
+        };
+        self.index.applied(inverse);
+        self.changed();
+        self.request_missing();
+        true
+    }
+
+    /// returns whether there was anything to redo
+    pub fn redo(&mut self) -> bool {
+        self.finish_drag();
+        self.finish_drawing();
+        self.state.selected = None;
+        self.state.selection.clear();
+        let Some(inverse) = self.history.redo(&mut self.items) else {
+            return false;
+        };
+        self.index.applied(inverse);
+        self.changed();
+        self.request_missing();
+        true
+    }
+
+    /// forgets all undo/redo steps and removes the cache of any items that only the history held onto
+    pub fn clear_history(&mut self) {
+        let lost = self.history.clear();
+        self.forget(lost);
     }
 
     pub fn add_image(
@@ -174,69 +1044,426 @@ impl Board {
     ) -> Result<(), Box<dyn std::error::Error>> {
         match kind {
             ImageType::Web(url) => {
-                self.add_choices_from_url(&url)?;
-                self.add_choices_images(ctx);
+                self.cancel_choices()?;
+                self.fetcher.request(&url, Purpose::Page);
             }
             ImageType::Online(url) => {
-                self.items.push(Item::Image(ItemImage::image_from_url(
-                    &self.store,
-                    &url,
-                    ctx,
-                )?));
+                self.push(Item::Image(ItemImage::placeholder(&url)));
+                self.request_missing();
             }
             ImageType::Local(path) => {
-                self.items.push(Item::Image(ItemImage::image_from_path(
-                    &self.store,
-                    &path,
-                    ctx,
-                )?));
+                let image = ItemImage::from_source(&mut self.store, &path, ctx)?;
+                self.push(Item::Image(image));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// starts downloading the images for each choice on the current page (up to [`Choices::CHOICE_AMOUNT`])
+    pub fn add_choices_images(&mut self) {
+        self.choices.load_page(&mut self.fetcher);
+    }
+
+    /// starts downloading the image of every placeholder which isnt already
+    fn request_missing(&mut self) {
+        for item in &self.items {
+            if let Item::Image(i) = item {
+                let purpose = Purpose::Item(i.id);
+                if i.handle.is_none() && i.failed.is_none() && !self.fetcher.is_pending(&purpose) {
+                    self.fetcher.request(i.kind.argument(), purpose);
+                }
+            }
+        }
+    }
+
+    /// stops every download of item images, giving back how many were stopped
+    pub fn cancel_downloads(&mut self) -> usize {
+        let cancelled = self.fetcher.cancel(|p| matches!(p, Purpose::Item(_)));
+        for item in self.items.iter_mut() {
+            if let Item::Image(i) = item {
+                if i.handle.is_none() && i.failed.is_none() {
+                    i.failed = Some("cancelled".to_owned());
+                }
+            }
+        }
+
+        cancelled
+    }
+
+    /// swaps the image `loaded` in for the placeholder at `i`, or marks it as broken if that failed
+    fn swap_in(&mut self, i: usize, loaded: Result<ItemImage, String>) -> Result<(), String> {
+        let Item::Image(image) = &mut self.items[i] else {
+            return Ok(());
+        };
+
+        match loaded {
+            Ok(loaded) => {
+                self.store.cache.retain(&loaded.blob);
+                if let Err(e) = self.store.cache.release(&image.blob) {
+                    println!("Error removing cached image \"{}\": {e}", image.blob);
+                }
+                image.set_handle(loaded);
+                if image.animated() {
+                    self.animated.insert(image.id);
+                }
+                self.index.moved(i);
+                self.changed();
+                Ok(())
+            }
+            Err(e) => {
+                image.failed = Some(e.clone());
+                Err(e)
+            }
+        }
+    }
+
+    /// tries loading the selected images which couldnt be loaded again, returns how many
+    /// local images are loaded right away, downloads finish in the background
+    pub fn retry_selection(&mut self, ctx: &Context) -> usize {
+        let broken = self
+            .state
+            .selection
+            .iter()
+            .copied()
+            .filter(|&i| matches!(&self.items[i], Item::Image(x) if x.failed.is_some()))
+            .collect::<Vec<usize>>();
+
+        for &i in &broken {
+            let Item::Image(image) = &mut self.items[i] else {
+                continue;
+            };
+            image.failed = None;
+            if let ImageType::Local(path) = &image.kind {
+                let path = path.clone();
+                let loaded =
+                    ItemImage::from_source(&mut self.store, &path, ctx).map_err(|e| e.to_string());
+                if let Err(e) = self.swap_in(i, loaded) {
+                    println!("couldnt load {path}: {e}");
+                }
             }
         }
+        self.request_missing();
+
+        broken.len()
+    }
+
+    /// points the selected images at the local file at `path` (which can be a `file://` uri or start with `~/`)
+    /// as one step, returns how many
+    pub fn relink_selection(
+        &mut self,
+        path: &str,
+        ctx: &Context,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        self.finish_drag();
+        let images = self
+            .state
+            .selection
+            .iter()
+            .copied()
+            .filter(|&i| matches!(self.items[i], Item::Image(_)))
+            .collect::<Vec<usize>>();
+        if images.is_empty() {
+            return Ok(0);
+        }
+
+        let path = match sniff::local_path(path) {
+            Some(p) if p.is_file() => p.display().to_string(),
+            _ => return Err(format!("{path} isnt a local file").into()),
+        };
+        let loaded = ItemImage::from_source(&mut self.store, &path, ctx)?;
+        let mut inverse = Vec::new();
+        for &i in &images {
+            let Item::Image(old) = &self.items[i] else {
+                continue;
+            };
+            let mut image = ItemImage::new(loaded.handle.clone().unwrap(), &path);
+            image.animation = loaded.animation.clone();
+            image.vector = loaded.vector.clone();
+            image.full_size = loaded.full_size;
+            image.tier = loaded.tier;
+            image.blob = loaded.blob.clone();
+            self.store.cache.retain(&image.blob);
+            let image = Item::Image(image)
+                .with_id(old.id)
+                .with_layer(old.layer)
+                .with_position(old.position)
+                .with_scale(old.scale)
+                .with_rotation(old.rotation);
+
+            self.track(&image);
+            // the old item is kept in the history so relinking can be undone
+            inverse.push(Edit::Remove(i).apply(&mut self.items));
+            inverse.push(Edit::Insert(i, image).apply(&mut self.items));
+        }
+        inverse.reverse();
+
+        let inverse = Edit::Group(inverse);
+        self.index.applied(&inverse);
+        self.record(inverse);
+        Ok(images.len())
+    }
+
+    /// handles any finished downloads, swapping in images for their placeholders
+    /// gives back messages about how they went
+    pub fn poll_downloads(&mut self, ctx: &Context) -> Vec<String> {
+        let mut messages = Vec::new();
+        for (purpose, source, result) in self.fetcher.poll() {
+            let name = Self::name_from_path(&source).to_owned();
+            match (purpose, result) {
+                (Purpose::Item(id), Ok(download)) => {
+                    let Some(i) = self.index.position(id) else {
+                        continue;
+                    };
+
+                    // it looked like an image but is a page, so its images are offered instead
+                    if sniff::sniff(&download.bytes, download.content_type.as_deref())
+                        == Content::Html
+                    {
+                        self.discard(i);
+                        messages.push(self.open_page(&download.bytes, &source));
+                        continue;
+                    }
+
+                    let loaded = self
+                        .store
+                        .cache
+                        .insert(&source, &download.bytes)
+                        .map_err(|e| e.to_string())
+                        .and_then(|blob| {
+                            ItemImage::from_blob(&self.store, blob, &source, ctx)
+                                .map_err(|e| e.to_string())
+                        });
+                    match self.swap_in(i, loaded) {
+                        Ok(()) => messages.push(format!("downloaded {name}")),
+                        Err(e) => messages.push(format!("couldnt load {name}: {e}")),
+                    }
+                }
+                (Purpose::Item(id), Err(e)) => {
+                    messages.push(format!("couldnt download {name}: {e}"));
+                    if let Some(Item::Image(image)) =
+                        self.index.position(id).map(|i| &mut self.items[i])
+                    {
+                        image.failed = Some(e);
+                    }
+                }
+
+                (Purpose::Page, Ok(download)) => {
+                    match sniff::sniff(&download.bytes, download.content_type.as_deref()) {
+                        // the url was an image after all
+                        Content::Image => {
+                            let image = self
+                                .store
+                                .cache
+                                .insert(&source, &download.bytes)
+                                .map_err(|e| e.to_string())
+                                .and_then(|blob| {
+                                    ItemImage::from_blob(&self.store, blob, &source, ctx)
+                                        .map_err(|e| e.to_string())
+                                });
+                            match image {
+                                Ok(image) => {
+                                    self.push(Item::Image(image));
+                                    messages.push(format!("added {name}"));
+                                }
+                                Err(e) => messages.push(format!("couldnt load {name}: {e}")),
+                            }
+                        }
+                        Content::Html | Content::Unknown => {
+                            messages.push(self.open_page(&download.bytes, &source))
+                        }
+                    }
+                }
+                (Purpose::Page, Err(e)) => messages.push(format!("couldnt load {source}: {e}")),
+
+                (Purpose::Choice, result) => {
+                    let image = result.and_then(|download| {
+                        let blob = self
+                            .store
+                            .cache
+                            .insert(&source, &download.bytes)
+                            .map_err(|e| e.to_string())?;
+                        ItemImage::from_blob(&self.store, blob, &source, ctx)
+                            .map_err(|e| e.to_string())
+                    });
+                    if let Err(e) = &image {
+                        println!("couldnt load choice {source}: {e}");
+                    }
+                    self.choices.loaded(&source, image.ok());
+                    self.add_choices_images();
+                }
+            }
+        }
+
+        let left = self.fetcher.len();
+        if !messages.is_empty() && left != 0 {
+            messages.push(format!("{left} downloads left"));
+        }
+        messages
+    }
+
+    /// whether theres choices waiting to be picked from
+    #[inline]
+    pub fn choosing(&self) -> bool {
+        !self.choices.is_empty()
+    }
+
+    /// moves the highlighted choice by `by` choices, loading the page its on
+    pub fn move_choice(&mut self, by: isize) {
+        self.choices.move_highlight(by);
+        self.add_choices_images();
+    }
+
+    /// moves the highlighted choice by `by` pages, loading the page its on
+    pub fn move_choice_page(&mut self, by: isize) {
+        self.choices.move_page(by);
+        self.add_choices_images();
+    }
+
+    /// index of the choice under the screen position `pos`
+    pub fn choice_at(&self, pos: (f32, f32)) -> Option<usize> {
+        self.choices.choice_at(pos, self.camera.resolution)
+    }
+
+    pub fn highlight_choice(&mut self, i: usize) {
+        self.choices.set_highlight(i)
+    }
+
+    /// turns the highlighted choice into an item at the screen position `pos` and discards the other choices
+    /// returns whether an item was added
+    pub fn confirm_choice(&mut self, pos: (f32, f32)) -> std::io::Result<bool> {
+        let Some(mut image) = self.choices.take_highlighted() else {
+            return Ok(false);
+        };
+
+        image.kind = ImageType::Online(image.kind.argument().to_owned());
+        image.position = self.camera.screen_to_world(pos);
+        self.push(Item::Image(image));
+        self.cancel_choices()?;
+
+        Ok(true)
+    }
+
+    /// discards all choices and removes the cached images of the ones that were loaded (unless an item uses them)
+    pub fn cancel_choices(&mut self) -> std::io::Result<()> {
+        self.fetcher
+            .cancel(|p| matches!(p, Purpose::Page | Purpose::Choice));
+        for blob in self.choices.clear() {
+            self.store.cache.remove_unused(&blob)?;
+        }
+
+        Ok(())
+    }
+
+    /// removes every cached image no item uses (including ones that could be brought back with undo/redo)
+    /// returns how many were removed
+    pub fn collect_garbage(&mut self) -> std::io::Result<usize> {
+        let keep = self.choices.blobs();
+        self.store.cache.collect_garbage(&keep)
+    }
+
+    pub fn draw_choices(&self, c: &mut Canvas, cc: &Context) {
+        self.choices
+            .draw(c, cc, self.camera.resolution, self.state.colours);
+    }
+
+    pub fn draw(&self, c: &mut Canvas, cc: &Context) {
+        self.screen_iter().for_each(|(i, x)| match x {
+            Item::Image(x) => x.draw(
+                self.camera,
+                c,
+                cc,
+                self.state.colours.1,
+                self.fetcher.progress(&Purpose::Item(x.id)),
+            ),
+            Item::Text(x) => x.draw(self.camera, c, self.index.rect(i), self.state.colours.1),
+            Item::Shape(x) => x.draw(self.camera, c, cc, self.state.colours.1),
+            Item::Pen(x) => x.draw(self.camera, c, cc, self.state.colours.1),
+            Item::Connector(x) => x.draw(self.camera, c, cc, self.state.colours.1),
+        });
+
+        let mut outlines = graphics::MeshBuilder::new();
+        for &i in &self.state.selection {
+            outlines
+                .polygon(
+                    graphics::DrawMode::stroke(2.0),
+                    &self.screen_corners(i),
+                    self.state.colours.1,
+                )
+                .expect("couldnt make the selection outline");
+        }
+        if let Some(Selectable::Marquee) = self.state.selected {
+            let r = self.camera.rect_to_screen(self.marquee_rect(cc));
+            outlines
+                .rectangle(
+                    graphics::DrawMode::stroke(1.0),
+                    Rect::new(r.0, r.1, r.2, r.3),
+                    self.state.colours.1,
+                )
+                .expect("couldnt make the marquee outline");
+        }
+        let outlines = outlines.build();
+        if !outlines.vertices.is_empty() {
+            c.draw(&graphics::Mesh::from_data(cc, outlines), DrawParam::new());
+        }
 
-        Ok(())
+        if let Some(editor) = &self.editor {
+            self.draw_caret(c, cc, editor);
+        }
     }
 
-    /// adds the images for each choice up to [`Self::CHOICE_AMOUNT`]
-    pub fn add_choices_images(&mut self, ctx: &Context) {
-        for c in self
-            .choices
-            .iter_mut()
-            .skip_while(|c| c.0.is_some())
-            .take(Self::CHOICE_AMOUNT)
-        {
-            *c = (
-                ItemImage::image_from_url(&self.store, &c.1, ctx).ok(),
-                c.1.clone(),
-            )
+    fn draw_caret(&self, c: &mut Canvas, cc: &Context, editor: &TextEditor) {
+        // blinks every half a second
+        if cc.time.time_since_start().as_millis() / 500 % 2 == 1 {
+            return;
         }
-    }
+        let Item::Text(item) = &self.items[editor.item()] else {
+            return;
+        };
+
+        // a bar is put at the caret so it can be found after wrapping and aligning
+        // its always a glyph so its the last one when the text before it is laid out on its own
+        let (before, rest) = editor.split_at_caret();
+        let glyphs = |s: String| item.layout(&s, 1.0).glyph_positions(cc).unwrap_or_default();
+        let n = glyphs(format!("{before}|")).len();
+        let Some(bar) = glyphs(format!("{before}|{rest}"))
+            .get(n.wrapping_sub(1))
+            .copied()
+        else {
+            return;
+        };
 
-    pub fn draw(&self, c: &mut Canvas, cc: &Context) {
-        self.screen_iter(cc).for_each(|x| match x {
-            Item::Image(x) => x.draw(self.camera, c),
-            Item::Text(x) => x.draw(self.camera, c, self.state.colours.1),
-        });
+        let rect = self.index.rect(editor.item());
+        // glyph positions are on the baseline
+        let x = item.style.align.offset(rect.rect.2) + bar.x;
+        let top = self
+            .camera
+            .world_to_screen(rect.world_point((x, bar.y - item.scale * 0.8)));
+        let bottom = self
+            .camera
+            .world_to_screen(rect.world_point((x, bar.y + item.scale * 0.2)));
+        c.draw(
+            &graphics::Mesh::new_line(
+                cc,
+                &[[top.0, top.1], [bottom.0, bottom.1]],
+                2.0,
+                self.state.colours.1,
+            )
+            .expect("couldnt make the caret"),
+            DrawParam::new(),
+        );
     }
 
     pub fn draw_bounds(&self, c: &mut Canvas, cc: &Context) {
         // debug rectangles
-        self.screen_iter(cc).enumerate().for_each(|(i, x)| {
+        self.screen_iter().for_each(|(i, _)| {
             c.draw(
-                &graphics::Mesh::new_rectangle(
+                &graphics::Mesh::new_polygon(
                     cc,
                     graphics::DrawMode::stroke(1.0),
-                    Rect::new(
-                        x.to_rect(self.camera, cc).0,
-                        x.to_rect(self.camera, cc).1,
-                        x.to_rect(self.camera, cc).2,
-                        x.to_rect(self.camera, cc).3,
-                    ),
-                    if let Some(Selectable::Item(s)) = self.state.selected {
-                        if s == i {
-                            Color::from_rgb(168, 50, 84)
-                        } else {
-                            Color::from_rgb(209, 65, 86)
-                        }
+                    &self.screen_corners(i),
+                    if self.state.selection.contains(&i) {
+                        Color::from_rgb(168, 50, 84)
                     } else {
                         Color::from_rgb(209, 65, 86)
                     },
@@ -253,10 +1480,6 @@ impl Board {
         }
 
         let screen_last = self.last_press_screen();
-        let world_mouse = self
-            .camera
-            .screen_to_world((cc.mouse.position().x, cc.mouse.position().y));
-
         c.draw(
             &graphics::Mesh::new_line(
                 cc,
@@ -267,93 +1490,162 @@ impl Board {
             .expect("couldnt draw the selection lines"),
             DrawParam::new(),
         );
-
-        c.draw(
-            &graphics::Mesh::new_line(
-                cc,
-                &[
-                    [self.state.last_press.0, self.state.last_press.1].into(),
-                    [world_mouse.0, world_mouse.1],
-                ],
-                1.0,
-                Color::BLUE,
-            )
-            .expect("couldnt draw the selection lines"),
-            DrawParam::new(),
-        )
     }
 
-    /// gets a list of images from a web source and adds their urls to the choice list
-    fn add_choices_from_url(&mut self, url: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let body = reqwest::blocking::get(url)?.text()?;
-        let img_regex = regex::Regex::new("<img.*>").unwrap();
-        // [4] would be the url
-        let url_regex =
-            regex::Regex::new(r#"src(\s*)=(\s*)("|')((http(s?):)?//(\?|.[^("|')])+)("|') "#)
-                .unwrap();
-
-        for m in img_regex.find_iter(&body) {
-            let Some(url) = url_regex.captures(m.as_str()) else {
-                println!("failed at url parsing for: {}", m.as_str());
-                continue;
-            };
+    /// offers the images on the web page `body` from `page` to pick from, giving back what happened
+    fn open_page(&mut self, body: &[u8], page: &str) -> String {
+        if let Err(e) = self.cancel_choices() {
+            println!("error while cancelling choices: {e}");
+        }
+        self.add_choices_from_page(&String::from_utf8_lossy(body), page);
+        self.add_choices_images();
 
-            println!("url: {}", url[4].to_owned());
-            self.choices.push((None, url[4].to_owned()));
+        if self.choosing() {
+            "pick an image".to_owned()
+        } else {
+            "couldnt find any images".to_owned()
         }
+    }
 
-        Ok(())
+    /// adds the urls of the images on the web page `body` which came from `page` to the choice list
+    fn add_choices_from_page(&mut self, body: &str, page: &str) {
+        for url in extract::images(body, page) {
+            self.choices.push(url);
+        }
     }
 
     #[inline]
     pub fn name_from_path(path: &str) -> &str {
-        path.split('/').last().unwrap_or(path)
+        path.split('/').next_back().unwrap_or(path)
         // sometimes urls have params so we dont want those
         // &name[0..name.find('?').unwrap_or(name.len()-1)]
     }
 
-    pub fn set_selection(&mut self, selection: Selectable) {
+    /// pressing an item outside the selection selects only it, otherwise the whole selection is managed
+    /// pressing the board or starting a marquee deselects everything
+    pub fn set_selection(&mut self, selection: Selectable, c: &Context) {
         self.state.selected = match selection {
             Selectable::Item(i) => {
-                // push to last so it gets drawn ontop
-                let last = self.items.len() - 1;
-                self.items.swap(i, last);
+                if !self.state.selection.contains(&i) {
+                    self.state.selection = vec![i];
+                }
+                self.refresh(c);
+
+                self.state.drag = Some(Drag {
+                    before: self
+                        .state
+                        .selection
+                        .iter()
+                        .map(|&s| (s, self.items[s].transform()))
+                        .collect(),
+                    centre: self.selection_centre(),
+                });
+
+                Some(Selectable::Item(i))
+            }
 
-                // self.state.selected = Some(i)
-                Some(Selectable::Item(last))
+            Selectable::Board | Selectable::Marquee | Selectable::Drawing => {
+                self.state.selection.clear();
+                Some(selection)
             }
+        }
+    }
 
-            Selectable::Board => Some(selection),
+    /// adds the item to the selection (and starts managing it) or removes it if its already selected
+    pub fn toggle_selection(&mut self, i: usize, c: &Context) {
+        match self.state.selection.iter().position(|&s| s == i) {
+            Some(p) => {
+                self.state.selection.remove(p);
+                // so the rest of the selection isnt dragged by an item which isnt in it anymore
+                self.finish_drag();
+                self.state.selected = None;
+            }
+            None => {
+                self.state.selection.push(i);
+                self.set_selection(Selectable::Item(i), c);
+            }
         }
     }
 
-    // iterater containing items only on screen
-    fn screen_iter<'a>(&'a self, c: &'a Context) -> impl Iterator<Item = &'a Item> {
-        self.items
-            .iter()
-            .filter(|i| self.camera.contains(i.to_rect(self.camera, c)))
+    /// indices of the selected items
+    pub fn selection(&self) -> &[usize] {
+        &self.state.selection
+    }
+
+    /// in world coords, the centre of the box around every selected item
+    fn selection_centre(&self) -> (f32, f32) {
+        let (min, max) = self.state.selection.iter().fold(
+            ((f32::MAX, f32::MAX), (f32::MIN, f32::MIN)),
+            |(min, max), &i| {
+                let r = self.index.rect(i).bounds();
+                (
+                    (min.0.min(r.0), min.1.min(r.1)),
+                    (max.0.max(r.0 + r.2), max.1.max(r.1 + r.3)),
+                )
+            },
+        );
+
+        ((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0)
     }
 
-    /// index corresponding to the selected item
-    pub fn select(&self, pos: (f32, f32), c: &Context) -> Option<usize> {
-        // TODO: quadtree optimisations
-        // TODO: take into account rotation
+    /// in world coords, the box between the last press and the mouse
+    /// rect: (x, y, w, h)
+    fn marquee_rect(&self, c: &Context) -> (f32, f32, f32, f32) {
+        let a = self.state.last_press;
+        let b = self
+            .camera
+            .screen_to_world((c.mouse.position().x, c.mouse.position().y));
 
-        /// rect `r`: (x, y, w, h)
-        #[inline]
-        fn inside(p: (f32, f32), r: (f32, f32, f32, f32)) -> bool {
-            (p.0 >= r.0 && p.0 <= r.0 + r.2) && (p.1 >= r.1 && p.1 <= r.1 + r.3)
-        }
+        (
+            a.0.min(b.0),
+            a.1.min(b.1),
+            (a.0 - b.0).abs(),
+            (a.1 - b.1).abs(),
+        )
+    }
+
+    /// visible items on screen, bottom to top
+    fn screen_iter(&self) -> impl Iterator<Item = (usize, &Item)> {
+        let mut found = self.index.query(self.camera.viewport());
+        found.retain(|&i| self.layers.visible(self.items[i].layer()));
+        // stable so items keep their order within a layer
+        found.sort_by_key(|&i| self.layers.rank(self.items[i].layer()));
+
+        found.into_iter().map(|i| (i, &self.items[i]))
+    }
+
+    /// corners of the (rotated) item at `i` in screen coords
+    fn screen_corners(&self, i: usize) -> [ggez::glam::Vec2; 4] {
+        self.index.rect(i).corners().map(|p| {
+            let p = self.camera.world_to_screen(p);
+            ggez::glam::Vec2::new(p.0, p.1)
+        })
+    }
+
+    /// index corresponding to the topmost item at the screen position `pos`
+    pub fn select(&mut self, pos: (f32, f32), c: &Context) -> Option<usize> {
+        self.refresh(c);
+        self.topmost(self.camera.screen_to_world(pos), |_| true)
+    }
 
-        self.screen_iter(c)
-            .position(|x| inside(pos, x.to_rect(self.camera, c)))
+    /// index of the topmost selectable item at the world position `p` which `f` lets through
+    fn topmost(&self, p: (f32, f32), f: impl Fn(usize) -> bool) -> Option<usize> {
+        self.index
+            .query_point(p)
+            .into_iter()
+            .filter(|&i| self.layers.selectable(self.items[i].layer()) && f(i))
+            .max_by_key(|&i| (self.layers.rank(self.items[i].layer()), i))
     }
 
     pub fn manage(&mut self, c: &Context) {
+        self.refresh(c);
+
         if (!c.mouse.button_pressed(MouseButton::Left)
             && !c.mouse.button_pressed(MouseButton::Right))
             || self.state.selected.is_none()
         {
+            self.finish_drag();
+            self.finish_drawing();
             self.state.selected = None;
             return;
         }
@@ -375,37 +1667,139 @@ impl Board {
 
         let mdelta = div_tuple((c.mouse.delta().x, c.mouse.delta().y), self.camera.zoom);
         match self.state.selected.unwrap() {
-            Selectable::Item(i) => {
-                let item = &mut self.items[i];
-
+            Selectable::Item(_) => {
                 // TODO: like proper square scale if shift is held
-                // scale
-                if c.keyboard.is_key_pressed(KeyCode::E)
+                let scaling = c.keyboard.is_key_pressed(KeyCode::E)
                     || (c.mouse.button_pressed(MouseButton::Left)
-                        && c.mouse.button_pressed(MouseButton::Right))
-                {
-                    match item {
-                        Item::Image(x) => x.scale = add_tuples(x.scale, div_tuple(mdelta, 100.0)),
-                        Item::Text(x) => x.scale += mdelta.0 + mdelta.1,
-                    }
-                }
-                // rotation
-                else if c.keyboard.is_key_pressed(KeyCode::R)
+                        && c.mouse.button_pressed(MouseButton::Right));
+                let rotating = c.keyboard.is_key_pressed(KeyCode::R)
                     || (c.mouse.button_pressed(MouseButton::Right)
-                        && !c.mouse.button_pressed(MouseButton::Left))
-                {
-                    match item {
-                        Item::Image(x) => x.rotation += (mdelta.0 + mdelta.1) / 180.,
-                        Item::Text(x) => x.rotation += (mdelta.0 + mdelta.1) / 180.,
+                        && !c.mouse.button_pressed(MouseButton::Left));
+
+                if let [i] = self.state.selection[..] {
+                    let item = &mut self.items[i];
+
+                    // scale
+                    if scaling {
+                        match item {
+                            Item::Image(x) => {
+                                x.scale = add_tuples(x.scale, div_tuple(mdelta, 100.0))
+                            }
+                            Item::Text(x) => x.scale += mdelta.0 + mdelta.1,
+                            Item::Shape(x) => {
+                                x.size = add_tuples(x.size, mdelta);
+                                x.size = (x.size.0.max(1.0), x.size.1.max(1.0));
+                            }
+                            Item::Pen(x) => x.scale = add_tuples(x.scale, div_tuple(mdelta, 100.0)),
+                            Item::Connector(_) => (),
+                        }
+                    }
+                    // rotation
+                    else if rotating {
+                        match item {
+                            Item::Image(x) => x.rotation += (mdelta.0 + mdelta.1) / 180.,
+                            Item::Text(x) => x.rotation += (mdelta.0 + mdelta.1) / 180.,
+                            Item::Shape(x) => x.rotation += (mdelta.0 + mdelta.1) / 180.,
+                            Item::Pen(x) => x.rotation += (mdelta.0 + mdelta.1) / 180.,
+                            Item::Connector(_) => (),
+                        }
+                    }
+                    // position
+                    else {
+                        match item {
+                            Item::Image(x) => x.position = add_tuples(x.position, mdelta),
+                            Item::Text(x) => x.position = add_tuples(x.position, mdelta),
+                            Item::Shape(x) => x.position = add_tuples(x.position, mdelta),
+                            Item::Pen(x) => x.position = add_tuples(x.position, mdelta),
+                            Item::Connector(_) => (),
+                        }
+                    }
+                } else if let Some(drag) = &mut self.state.drag {
+                    // groups are scaled and rotated around their common centre
+                    let centre = drag.centre;
+                    if !scaling && !rotating {
+                        drag.centre = add_tuples(drag.centre, mdelta);
+                    }
+
+                    for &i in &self.state.selection {
+                        let mut t = self.items[i].transform();
+                        let offset = sub_tuples(t.position, centre);
+
+                        // scale
+                        if scaling {
+                            let by = (1.0 + (mdelta.0 + mdelta.1) / 100.0).max(0.01);
+                            t.scale = (t.scale.0 * by, t.scale.1 * by);
+                            t.position = add_tuples(centre, (offset.0 * by, offset.1 * by));
+                        }
+                        // rotation
+                        // items rotate around their own centre so its their centre that gets moved around the groups
+                        else if rotating {
+                            let (sin, cos) = ((mdelta.0 + mdelta.1) / 180.).sin_cos();
+                            // as of the refresh at the start, so text isnt measured again each frame
+                            let item_centre = self.index.rect(i).centre();
+                            let offset = sub_tuples(item_centre, centre);
+                            let rotated = add_tuples(
+                                centre,
+                                (
+                                    offset.0 * cos - offset.1 * sin,
+                                    offset.0 * sin + offset.1 * cos,
+                                ),
+                            );
+
+                            t.rotation += (mdelta.0 + mdelta.1) / 180.;
+                            t.position = add_tuples(t.position, sub_tuples(rotated, item_centre));
+                        }
+                        // position
+                        else {
+                            t.position = add_tuples(t.position, mdelta);
+                        }
+
+                        self.items[i].set_transform(t);
                     }
                 }
-                // position
-                else {
-                    match item {
-                        Item::Image(x) => x.position = add_tuples(x.position, mdelta),
-                        Item::Text(x) => x.position = add_tuples(x.position, mdelta),
+
+                if mdelta != (0.0, 0.0) {
+                    self.state
+                        .selection
+                        .iter()
+                        .for_each(|&i| self.index.moved(i));
+                    self.refresh(c);
+                }
+            }
+
+            Selectable::Drawing => {
+                let mouse = self
+                    .camera
+                    .screen_to_world((c.mouse.position().x, c.mouse.position().y));
+                let last = self.items.len() - 1;
+
+                match &mut self.items[last] {
+                    Item::Shape(x) => x.span(self.state.last_press, mouse),
+                    Item::Pen(x) => {
+                        // only every few pixels on screen so strokes dont get too big
+                        let p = x.last_point();
+                        if (mouse.0 - p.0).hypot(mouse.1 - p.1) * self.camera.zoom < 3.0 {
+                            return;
+                        }
+                        x.push_point(mouse);
                     }
+                    _ => (),
                 }
+                self.index.moved(last);
+                self.refresh(c);
+            }
+
+            Selectable::Marquee => {
+                let marquee = self.marquee_rect(c);
+                self.state.selection = self
+                    .index
+                    .query(marquee)
+                    .into_iter()
+                    .filter(|&i| {
+                        self.layers.selectable(self.items[i].layer())
+                            && self.index.rect(i).intersects(marquee)
+                    })
+                    .collect();
             }
 
             Selectable::Board => {
@@ -417,7 +1811,7 @@ impl Board {
                 }
                 // pivot
                 else if c.mouse.button_pressed(MouseButton::Left) {
-                    self.camera.centre = add_tuples(self.camera.centre, mdelta)
+                    self.camera.centre = sub_tuples(self.camera.centre, mdelta)
                 }
                 // glide
                 else if c.mouse.button_pressed(MouseButton::Right) {
@@ -426,10 +1820,8 @@ impl Board {
                         div_tuple(
                             sub_tuples(
                                 self.state.last_press,
-                                self.camera.screen_to_world(div_tuple(
-                                    (c.mouse.position().x, c.mouse.position().y),
-                                    self.camera.zoom
-                                ))
+                                self.camera
+                                    .screen_to_world((c.mouse.position().x, c.mouse.position().y)),
                             ),
                             10.0,
                         ),
@@ -439,6 +1831,108 @@ impl Board {
         }
     }
 
+    #[inline]
+    pub fn set_tool(&mut self, tool: Tool) {
+        self.state.tool = tool;
+    }
+
+    /// starts drawing with the current tool at the screen position `pos`
+    /// returns whether anything is being drawn
+    pub fn start_drawing(&mut self, pos: (f32, f32), c: &Context) -> bool {
+        let start = self.camera.screen_to_world(pos);
+        let mut item = match self.state.tool {
+            Tool::Select => return false,
+            Tool::Pen => Item::Pen(ItemPen::new(ShapeStyle::default(), start)),
+            tool => Item::Shape(ItemShape::for_tool(tool, ShapeStyle::default()).unwrap()),
+        };
+
+        self.state.last_press = start;
+        item.set_id(self.new_id());
+        item.set_layer(self.layers.current().id);
+        self.items.push(item);
+        self.index.inserted(self.items.len() - 1);
+        self.set_selection(Selectable::Drawing, c);
+        true
+    }
+
+    /// keeps the drawn item as one step (selecting it) unless its too small to see
+    fn finish_drawing(&mut self) {
+        let Some(Selectable::Drawing) = self.state.selected else {
+            return;
+        };
+        let last = self.items.len() - 1;
+
+        let empty = match &self.items[last] {
+            Item::Shape(x) => x.is_empty(),
+            Item::Pen(x) => x.is_empty(),
+            _ => false,
+        };
+        if empty {
+            self.items.pop();
+            self.index.removed(last);
+        } else {
+            self.attach(last);
+            self.record(Edit::Remove(last));
+            self.state.selection = vec![last];
+        }
+    }
+
+    /// turns the arrow at `i` into a connector if its drawn from one item to another,
+    /// so it stays attached to them as theyre moved
+    fn attach(&mut self, i: usize) {
+        let Item::Shape(arrow) = &self.items[i] else {
+            return;
+        };
+        // connectors only have an arrowhead at the end
+        let (
+            ShapeKind::Line {
+                arrows: (false, true),
+            },
+            Some((start, end)),
+        ) = (arrow.kind, arrow.ends())
+        else {
+            return;
+        };
+        let under = |p| {
+            self.topmost(p, |j| {
+                j != i && !matches!(self.items[j], Item::Connector(_))
+            })
+        };
+        let (Some(from), Some(to)) = (under(start), under(end)) else {
+            return;
+        };
+        if from == to {
+            return;
+        }
+
+        let mut connector = ItemConnector::new(self.items[from].id(), self.items[to].id());
+        connector.style = arrow.style.clone();
+        self.items[i] = Item::Connector(connector)
+            .with_id(arrow.id)
+            .with_layer(arrow.layer);
+        // indexed again as a connector so its routed
+        self.index.removed(i);
+        self.index.inserted(i);
+    }
+
+    /// records the whole drag (moving, scaling, rotating) as one step
+    fn finish_drag(&mut self) {
+        let Some(drag) = self.state.drag.take() else {
+            return;
+        };
+
+        let inverse = drag
+            .before
+            .into_iter()
+            .filter(|&(i, before)| self.items[i].transform() != before)
+            .map(|(i, before)| Edit::Transform(i, before))
+            .collect::<Vec<Edit>>();
+
+        if !inverse.is_empty() {
+            self.record(Edit::Group(inverse));
+        }
+    }
+
     pub fn last_press_screen(&self) -> (f32, f32) {
         self.camera.world_to_screen(self.state.last_press)
     }
@@ -447,17 +1941,39 @@ impl Board {
         self.state.selected
     }
 
-    pub fn remove(&mut self, i: usize) -> std::io::Result<()> {
-        let item = &self.items[i];
-        match item {
-            Item::Image(i) => self.store.remove_cached(i.kind.argument())?,
-            _ => {}
-        }
+    /// removes every selected item as one step, returning how many were removed
+    /// the cached image of a removed item is only deleted once the removal cant be undone
+    pub fn remove_selection(&mut self) -> usize {
+        self.finish_drag();
 
-        self.items.remove(i);
+        let mut selection = std::mem::take(&mut self.state.selection);
+        // connectors cant be left hanging
+        let removed = selection
+            .iter()
+            .map(|&i| self.items[i].id())
+            .collect::<Vec<ItemId>>();
+        selection.extend(self.items.iter().enumerate().filter_map(|(i, x)| match x {
+            Item::Connector(x) if removed.contains(&x.from) || removed.contains(&x.to) => Some(i),
+            _ => None,
+        }));
+        selection.sort_unstable();
+        selection.dedup();
+
+        let mut inverse = selection
+            .iter()
+            .rev()
+            .map(|&i| Edit::Insert(i, self.items.remove(i)))
+            .collect::<Vec<Edit>>();
+        inverse.reverse();
+
+        if !inverse.is_empty() {
+            let inverse = Edit::Group(inverse);
+            self.index.applied(&inverse);
+            self.record(inverse);
+        }
         self.state.selected = None;
 
-        Ok(())
+        selection.len()
     }
 
     pub fn get(&self, i: usize) -> Option<&Item> {
@@ -469,12 +1985,33 @@ impl Board {
     }
 
     pub fn save(&mut self) -> std::io::Result<()> {
-        self.store.clear()?;
-        Ok(self.items.iter().for_each(|x| {
-            if let Err(e) = self.store.add(x) {
-                println!("Error saving an item ({x:?}): \"{e}\"");
-            }
-        }))
+        store::write_atomic(
+            &self.store.layers,
+            serde_json::to_string_pretty(&self.layers)?.as_bytes(),
+        )?;
+
+        self.store.save(&self.items)?;
+        self.saved = merge::snapshot(&self.items);
+        self.unsaved = None;
+
+        Ok(())
+    }
+
+    /// packs the board as it was last saved into a single archive at `to`
+    pub fn export(
+        &self,
+        to: &std::path::Path,
+    ) -> Result<archive::Manifest, Box<dyn std::error::Error>> {
+        let mut images = Self::blobs(&self.items).collect::<Vec<&str>>();
+        images.sort_unstable();
+        images.dedup();
+
+        let root = self
+            .store
+            .store
+            .parent()
+            .unwrap_or(std::path::Path::new("."));
+        archive::export(root, &images, to)
     }
 
     pub fn set_colours(&mut self, c: (Color, Color)) {
@@ -487,27 +2024,86 @@ impl Board {
 }
 
 impl ItemImage {
+    /// how big images are in world units while theyre being downloaded
+    const PLACEHOLDER_SIZE: (f32, f32) = (200.0, 200.0);
+    /// the tier of a big image none of which has been loaded yet, so its loaded at whichever tier suits it
+    const NOT_LOADED: Option<u32> = Some(0);
+
     pub fn new(handle: Image, argument: &str) -> Self {
         Self {
             handle: Some(handle),
+            ..Self::placeholder(argument)
+        }
+    }
+
+    /// an image which is shown as a box until its downloaded
+    pub fn placeholder(argument: &str) -> Self {
+        Self {
+            id: 0,
+            layer: 0,
+            handle: None,
+            failed: None,
+            animation: None,
+            vector: None,
+            full_size: None,
+            tier: None,
+            paused: false,
+            blob: String::new(),
             position: (0., 0.),
             scale: (1., 1.),
             rotation: 0.,
-            kind: ImageType::type_from_argument(argument),
+            // its an image whatever the url looks like
+            kind: match ImageType::type_from_argument(argument) {
+                ImageType::Web(url) => ImageType::Online(url),
+                kind => kind,
+            },
         }
     }
 
-    pub fn handle<'a>(&'a self) -> &'a Image {
-        // this should never be None so its okay to unwrap
-        self.handle.as_ref().unwrap()
+    /// the frame (or raster) being shown, [`None`] while its being downloaded
+    #[inline]
+    pub fn handle(&self) -> Option<&Image> {
+        self.animation
+            .as_ref()
+            .map(|a| a.frame())
+            .or(self.vector.as_ref().map(|v| v.image()))
+            .or(self.handle.as_ref())
+    }
+
+    #[inline]
+    pub fn animated(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// swaps in the downloaded image
+    pub fn set_handle(&mut self, loaded: ItemImage) {
+        self.handle = loaded.handle;
+        self.animation = loaded.animation;
+        self.vector = loaded.vector;
+        self.full_size = loaded.full_size;
+        self.tier = loaded.tier;
+        self.blob = loaded.blob;
+        self.failed = None;
+    }
+
+    /// size in pixels of the image (or placeholder), svgs being their own size whatever theyre rasterised at
+    /// and images their full size whichever thumbnail is shown
+    pub fn size(&self) -> (f32, f32) {
+        if let Some(vector) = &self.vector {
+            return vector.size();
+        }
+        if let Some((width, height)) = self.full_size {
+            return (width as f32, height as f32);
+        }
+        self.handle.as_ref().map_or(Self::PLACEHOLDER_SIZE, |h| {
+            (h.width() as f32, h.height() as f32)
+        })
     }
 
+    /// where the item is drawn from on screen so that it rotates around its centre
     #[inline]
     pub fn world_position(&self, camera: Camera) -> (f32, f32) {
-        (
-            (self.position.0 + camera.centre.0) * camera.zoom,
-            (self.position.1 + camera.centre.1) * camera.zoom,
-        )
+        camera.world_to_screen(self.oriented().origin())
     }
 
     #[inline]
@@ -515,69 +2111,176 @@ impl ItemImage {
         (self.scale.0 * camera.zoom, self.scale.1 * camera.zoom)
     }
 
-    fn draw(&self, cam: Camera, c: &mut Canvas) {
+    /// placeholders are drawn as a box with how far along the download is (`progress` from 0 to 1)
+    /// `text` being the boards text colour
+    fn draw(&self, cam: Camera, c: &mut Canvas, cc: &Context, text: Color, progress: Option<f32>) {
+        let Some(handle) = self.handle() else {
+            let corners = self.oriented().corners().map(|p| {
+                let p = cam.world_to_screen(p);
+                [p.0, p.1]
+            });
+            let mut mesh = graphics::MeshBuilder::new();
+            _ = mesh.polygon(graphics::DrawMode::stroke(2.0), &corners, text);
+            // crossed out when its broken
+            if self.failed.is_some() {
+                _ = mesh.line(&[corners[0], corners[2]], 2.0, text);
+                _ = mesh.line(&[corners[1], corners[3]], 2.0, text);
+            }
+            shape::draw_mesh(c, cc, &mesh);
+
+            let status = match (&self.failed, progress) {
+                (Some(e), _) => format!(
+                    "couldnt load {}\n{e}\nC-R to retry, C-P to relink",
+                    self.kind.argument()
+                ),
+                (None, Some(p)) => format!("loading {:.0}%", p * 100.0),
+                (None, None) => "loading".to_owned(),
+            };
+            let (w, _) = self.world_scale(cam);
+            c.draw(
+                Text::new(status)
+                    .set_scale(16.0)
+                    .set_bounds([Self::PLACEHOLDER_SIZE.0 * w - 8.0, f32::INFINITY]),
+                DrawParam::new()
+                    .dest([corners[0][0] + 4.0, corners[0][1] + 4.0])
+                    .rotation(self.rotation)
+                    .color(text),
+            );
+            return;
+        };
+
+        // rasters of svgs and thumbnails arent the size of the image
+        let raster = (
+            handle.width() as f32 / self.size().0,
+            handle.height() as f32 / self.size().1,
+        );
         c.draw(
-            self.handle(),
+            handle,
             DrawParam::new()
                 .dest([self.world_position(cam).0, self.world_position(cam).1])
-                .scale([self.world_scale(cam).0, self.world_scale(cam).1])
+                .scale([
+                    self.world_scale(cam).0 / raster.0,
+                    self.world_scale(cam).1 / raster.1,
+                ])
                 .rotation(self.rotation)
                 .color(Color::WHITE),
         );
     }
 
-    pub fn to_rect(&self, cam: Camera) -> (f32, f32, f32, f32) {
+    /// rect in world coords before rotation: (x, y, w, h)
+    pub fn world_rect(&self) -> (f32, f32, f32, f32) {
         (
-            self.world_position(cam).0,
-            self.world_position(cam).1,
-            self.world_scale(cam).0 * self.handle().width() as f32,
-            self.world_scale(cam).1 * self.handle().height() as f32,
+            self.position.0,
+            self.position.1,
+            self.scale.0 * self.size().0,
+            self.scale.1 * self.size().1,
         )
     }
 
-    /// downloads image from url, caches it in our storage, gives a handle to it
-    pub fn image_from_url(
-        store: &Store,
-        url: &str,
-        ctx: &Context,
-    ) -> Result<ItemImage, Box<dyn std::error::Error>> {
-        let img_bytes = reqwest::blocking::get(url)?.bytes()?.to_vec();
+    #[inline]
+    pub fn oriented(&self) -> OrientedRect {
+        OrientedRect::new(self.world_rect(), self.rotation)
+    }
 
-        let path = store.cache.join(Board::name_from_path(url));
-        let mut file = std::fs::File::create(&path)?;
-        file.write(&img_bytes)?;
+    /// the blob of the local (or already cached) image at `source`, copying it into the cache if it isnt already
+    /// downloads go through the [`Fetcher`] instead so they dont hold up the window
+    pub fn fetch(store: &mut Store, source: &str) -> Result<String, Box<dyn std::error::Error>> {
+        if let Some(blob) = store.cache.lookup(source) {
+            return Ok(blob.to_owned());
+        }
+        if source.starts_with("http") {
+            return Err(format!("{source} isnt downloaded yet").into());
+        }
 
-        ItemImage::from_path(store, url, ctx).map_err(Box::from)
+        Ok(store.cache.insert(source, &std::fs::read(source)?)?)
     }
 
-    pub fn image_from_path(
-        store: &Store,
-        path: &str,
+    /// the local (or already cached) image at `source`, giving a handle to it
+    pub fn from_source(
+        store: &mut Store,
+        source: &str,
         ctx: &Context,
     ) -> Result<ItemImage, Box<dyn std::error::Error>> {
-        let cache_path = store.cache.join(Board::name_from_path(path));
-        std::fs::copy(path, cache_path)?;
+        let blob = Self::fetch(store, source)?;
+        ItemImage::from_blob(store, blob, source, ctx).map_err(Box::from)
+    }
 
-        ItemImage::from_path(store, path, ctx).map_err(Box::from)
+    /// starts decoding the thumbnail `tier` (or the full image for [`None`]) to show in place of the one shown,
+    /// unless its the one already shown or being decoded
+    fn request_tier(&mut self, fetcher: &Fetcher, cache: &Cache, tier: Option<u32>) {
+        if tier != self.tier {
+            self.tier = tier;
+            fetcher.decode(self.id, &cache.dir, &self.blob, tier);
+        }
     }
 
-    pub fn from_path(store: &Store, argument: &str, ctx: &Context) -> ggez::GameResult<Self> {
-        Ok(ItemImage::new(
-            graphics::Image::from_path(
-                ctx,
-                std::path::PathBuf::from("/")
-                    .join(store.cache.clone())
-                    .join(Board::name_from_path(argument)),
-            )?,
-            argument,
-        ))
+    pub fn from_blob(
+        store: &Store,
+        blob: String,
+        argument: &str,
+        ctx: &Context,
+    ) -> ggez::GameResult<Self> {
+        let bytes = read_cached(ctx, store.cache.path(&blob))?;
+
+        let vector = Vector::decode(ctx, &bytes).map_err(|e| {
+            ggez::GameError::ResourceLoadError(format!("couldnt read the svg: {e}"))
+        })?;
+        if let Some(vector) = vector {
+            let mut image = ItemImage::new(vector.image().clone(), argument);
+            image.vector = Some(Box::new(vector));
+            image.blob = blob;
+            return Ok(image);
+        }
+
+        // big images start out as their smallest thumbnail, the full image is only loaded once its needed
+        let full_size = cache::thumbnail_size(&bytes).filter(|s| s.0.max(s.1) > cache::TIERS[0]);
+        if let Some(full_size) = full_size {
+            let thumbnail = read_cached(ctx, store.cache.thumbnail_path(&blob, cache::TIERS[0]))
+                .and_then(|bytes| graphics::Image::from_bytes(ctx, &bytes));
+            let (handle, tier) = match thumbnail {
+                Ok(handle) => (handle, Some(cache::TIERS[0])),
+                // still being made in the background (or cached from before thumbnails, so its made
+                // once its near the screen) and until then its a faint box
+                Err(_) => (
+                    Image::from_color(ctx, 1, 1, Some(Color::new(0.5, 0.5, 0.5, 0.25))),
+                    Self::NOT_LOADED,
+                ),
+            };
+            let mut image = ItemImage::new(handle, argument);
+            image.full_size = Some(full_size);
+            image.tier = tier;
+            image.blob = blob;
+            return Ok(image);
+        }
+
+        let animation = Animation::decode(ctx, &bytes).unwrap_or_else(|e| {
+            println!("couldnt decode the animation in {argument}, showing one frame: {e}");
+            None
+        });
+        let mut image = ItemImage::new(graphics::Image::from_bytes(ctx, &bytes)?, argument);
+        image.animation = animation.map(Box::new);
+        image.blob = blob;
+        Ok(image)
     }
 }
 
+/// the file at `path` in the cache, read through ggez since thats where its resource paths point
+fn read_cached(ctx: &Context, path: std::path::PathBuf) -> ggez::GameResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ctx.fs
+        .open(std::path::PathBuf::from("/").join(path))?
+        .read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
 impl ItemText {
     pub fn new(text: String) -> Self {
         Self {
+            id: 0,
+            layer: 0,
             text,
+            style: TextStyle::default(),
+            editing: false,
             position: (0., 0.),
             scale: 100.,
             rotation: 0.0,
@@ -585,46 +2288,133 @@ impl ItemText {
     }
 
     #[inline]
-    pub fn text(&self, camera: Camera) -> Text {
-        Text::new(&self.text)
-            .set_scale(self.scale * camera.zoom)
-            .clone()
+    pub fn contents(&self) -> &str {
+        &self.text
     }
 
+    /// returns the old contents
     #[inline]
-    pub fn world_position(&self, camera: Camera) -> (f32, f32) {
-        (
-            (self.position.0 + camera.centre.0) * camera.zoom,
-            (self.position.1 + camera.centre.1) * camera.zoom,
-        )
+    pub fn set_contents(&mut self, text: String) -> String {
+        std::mem::replace(&mut self.text, text)
     }
 
-    fn draw(&self, cam: Camera, c: &mut Canvas, colour: Color) {
+    /// `s` in this items style at `zoom`
+    #[inline]
+    fn layout(&self, s: &str, zoom: f32) -> Text {
+        self.style.text(s, self.scale * zoom, !self.editing)
+    }
+
+    /// `rect` being the items rect (which is expensive to measure every frame)
+    fn draw(&self, cam: Camera, c: &mut Canvas, rect: OrientedRect, colour: Color) {
+        // drawn from the origin so that it rotates around its centre
+        let position =
+            cam.world_to_screen(rect.world_point((self.style.align.offset(rect.rect.2), 0.0)));
         c.draw(
-            &self.text(cam),
+            &self.layout(&self.text, cam.zoom),
             DrawParam::new()
-                .dest([self.world_position(cam).0, self.world_position(cam).1])
+                .dest([position.0, position.1])
                 .rotation(self.rotation)
                 .color(colour),
         );
     }
 
-    pub fn to_rect(&self, cam: Camera, c: &Context) -> (f32, f32, f32, f32) {
-        let dim = self.text(cam).measure(c).unwrap();
-        (
-            self.world_position(cam).0,
-            self.world_position(cam).1,
-            dim.x,
-            dim.y,
-        )
+    /// rect in world coords before rotation: (x, y, w, h)
+    pub fn world_rect(&self, c: &Context) -> (f32, f32, f32, f32) {
+        let dim = self.layout(&self.text, 1.0).measure(c).unwrap();
+        (self.position.0, self.position.1, dim.x, dim.y)
+    }
+
+    #[inline]
+    pub fn oriented(&self, c: &Context) -> OrientedRect {
+        OrientedRect::new(self.world_rect(c), self.rotation)
     }
 }
 
 impl Item {
+    pub fn id(&self) -> ItemId {
+        match self {
+            Item::Image(i) => i.id,
+            Item::Text(i) => i.id,
+            Item::Shape(i) => i.id,
+            Item::Pen(i) => i.id,
+            Item::Connector(i) => i.id,
+        }
+    }
+
+    pub fn set_id(&mut self, id: ItemId) {
+        match self {
+            Item::Image(i) => i.id = id,
+            Item::Text(i) => i.id = id,
+            Item::Shape(i) => i.id = id,
+            Item::Pen(i) => i.id = id,
+            Item::Connector(i) => i.id = id,
+        }
+    }
+
+    pub fn with_id(mut self, id: ItemId) -> Self {
+        self.set_id(id);
+        self
+    }
+
+    pub fn layer(&self) -> LayerId {
+        match self {
+            Item::Image(i) => i.layer,
+            Item::Text(i) => i.layer,
+            Item::Shape(i) => i.layer,
+            Item::Pen(i) => i.layer,
+            Item::Connector(i) => i.layer,
+        }
+    }
+
+    pub fn set_layer(&mut self, layer: LayerId) {
+        match self {
+            Item::Image(i) => i.layer = layer,
+            Item::Text(i) => i.layer = layer,
+            Item::Shape(i) => i.layer = layer,
+            Item::Pen(i) => i.layer = layer,
+            Item::Connector(i) => i.layer = layer,
+        }
+    }
+
+    pub fn with_layer(mut self, layer: LayerId) -> Self {
+        self.set_layer(layer);
+        self
+    }
+
+    /// the text of text items or the label of connectors
+    pub fn contents(&self) -> Option<&str> {
+        match self {
+            Item::Text(i) => Some(i.contents()),
+            Item::Connector(i) => Some(i.label()),
+            _ => None,
+        }
+    }
+
+    /// sets the text of text items or the label of connectors, returning the old contents
+    pub fn set_contents(&mut self, contents: String) -> String {
+        match self {
+            Item::Text(i) => i.set_contents(contents),
+            Item::Connector(i) => i.set_label(contents),
+            _ => contents,
+        }
+    }
+
+    /// whether the item is being typed into
+    pub fn set_editing(&mut self, editing: bool) {
+        match self {
+            Item::Text(i) => i.editing = editing,
+            Item::Connector(i) => i.editing = editing,
+            _ => (),
+        }
+    }
+
     pub fn with_position(mut self, pos: (f32, f32)) -> Self {
         match self {
             Item::Text(ref mut i) => i.position = pos,
             Item::Image(ref mut i) => i.position = pos,
+            Item::Shape(ref mut i) => i.position = pos,
+            Item::Pen(ref mut i) => i.position = pos,
+            Item::Connector(_) => (),
         }
 
         self
@@ -635,24 +2425,91 @@ impl Item {
         match self {
             Item::Text(ref mut i) => i.scale = scale.0,
             Item::Image(ref mut i) => i.scale = scale,
+            Item::Shape(ref mut i) => i.size = scale,
+            Item::Pen(ref mut i) => i.scale = scale,
+            Item::Connector(_) => (),
         }
 
         self
     }
 
+    pub fn transform(&self) -> Transform {
+        match self {
+            Item::Text(i) => Transform {
+                position: i.position,
+                scale: (i.scale, i.scale),
+                rotation: i.rotation,
+            },
+            Item::Image(i) => Transform {
+                position: i.position,
+                scale: i.scale,
+                rotation: i.rotation,
+            },
+            Item::Shape(i) => Transform {
+                position: i.position,
+                scale: i.size,
+                rotation: i.rotation,
+            },
+            Item::Pen(i) => Transform {
+                position: i.position,
+                scale: i.scale,
+                rotation: i.rotation,
+            },
+            // connectors only go where their ends are
+            Item::Connector(_) => Transform {
+                position: (0.0, 0.0),
+                scale: (1.0, 1.0),
+                rotation: 0.0,
+            },
+        }
+    }
+
+    pub fn set_transform(&mut self, t: Transform) {
+        match self {
+            Item::Text(i) => {
+                i.position = t.position;
+                i.scale = t.scale.0;
+                i.rotation = t.rotation;
+            }
+            Item::Image(i) => {
+                i.position = t.position;
+                i.scale = t.scale;
+                i.rotation = t.rotation;
+            }
+            Item::Shape(i) => {
+                i.position = t.position;
+                i.size = t.scale;
+                i.rotation = t.rotation;
+            }
+            Item::Pen(i) => {
+                i.position = t.position;
+                i.scale = t.scale;
+                i.rotation = t.rotation;
+            }
+            Item::Connector(_) => (),
+        }
+    }
+
     pub fn with_rotation(mut self, rotation: f32) -> Self {
         match self {
             Item::Text(ref mut i) => i.rotation = rotation,
             Item::Image(ref mut i) => i.rotation = rotation,
+            Item::Shape(ref mut i) => i.rotation = rotation,
+            Item::Pen(ref mut i) => i.rotation = rotation,
+            Item::Connector(_) => (),
         }
 
         self
     }
 
-    pub fn to_rect(&self, cam: Camera, c: &Context) -> (f32, f32, f32, f32) {
+    /// the (rotated) rect covered by the item in world coords
+    pub fn oriented(&self, c: &Context) -> OrientedRect {
         match self {
-            Self::Text(i) => i.to_rect(cam, c),
-            Self::Image(i) => i.to_rect(cam),
+            Self::Text(i) => i.oriented(c),
+            Self::Image(i) => i.oriented(),
+            Self::Shape(i) => i.oriented(),
+            Self::Pen(i) => i.oriented(),
+            Self::Connector(i) => i.oriented(),
         }
     }
 }
@@ -665,7 +2522,119 @@ impl std::fmt::Display for Item {
             match self {
                 Item::Text(_) => "Text",
                 Item::Image(_) => "Image",
+                Item::Shape(i) => match i.kind {
+                    ShapeKind::Rectangle => "Rectangle",
+                    ShapeKind::Ellipse => "Ellipse",
+                    ShapeKind::Line {
+                        arrows: (false, false),
+                    } => "Line",
+                    ShapeKind::Line { .. } => "Arrow",
+                },
+                Item::Pen(_) => "Pen stroke",
+                Item::Connector(_) => "Connector",
             }
         )
     }
 }
+
+/// top left corners for items of `sizes` laid out in rows from `from`, about as many rows as columns
+fn grid(sizes: &[(f32, f32)], from: (f32, f32)) -> Vec<(f32, f32)> {
+    const GAP: f32 = 20.0;
+
+    let columns = (sizes.len() as f32).sqrt().ceil().max(1.0) as usize;
+    let mut corners = Vec::with_capacity(sizes.len());
+    let mut y = from.1;
+    for row in sizes.chunks(columns) {
+        let mut x = from.0;
+        for size in row {
+            corners.push((x, y));
+            x += size.0 + GAP;
+        }
+        y += row.iter().map(|s| s.1).fold(0.0, f32::max) + GAP;
+    }
+
+    corners
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{depth_order, grid, Depth, Item, ItemImage};
+
+    #[test]
+    fn dropped_items_are_laid_out_in_rows() {
+        assert_eq!(grid(&[(50.0, 50.0)], (5.0, 5.0)), [(5.0, 5.0)]);
+
+        let sizes = [
+            (100.0, 50.0),
+            (10.0, 80.0),
+            (30.0, 30.0),
+            (40.0, 40.0),
+            (5.0, 5.0),
+        ];
+        assert_eq!(
+            grid(&sizes, (0.0, 0.0)),
+            [
+                (0.0, 0.0),
+                (120.0, 0.0),
+                (150.0, 0.0),
+                (0.0, 100.0),
+                (60.0, 100.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn reordering_stays_within_layers() {
+        // two layers interleaved in the list, the selected items being 1 and 3
+        let layers = [0, 1, 0, 1, 1, 0];
+        let selected = [false, true, false, true, false, false];
+
+        assert_eq!(
+            depth_order(&layers, &selected, Depth::Forward),
+            [0, 4, 2, 1, 3, 5]
+        );
+        // theres nothing under them on their own layer
+        assert_eq!(
+            depth_order(&layers, &selected, Depth::Backward),
+            [0, 1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            depth_order(&layers, &selected, Depth::Front),
+            [0, 2, 4, 5, 1, 3]
+        );
+        assert_eq!(
+            depth_order(&layers, &selected, Depth::Back),
+            [1, 3, 0, 2, 4, 5]
+        );
+
+        // passing an item on another layer to swap with the one under it on its own
+        assert_eq!(
+            depth_order(&[1, 0, 1], &[false, false, true], Depth::Backward),
+            [2, 1, 0]
+        );
+        assert_eq!(
+            depth_order(&[1, 0, 1], &[true, false, false], Depth::Forward),
+            [2, 1, 0]
+        );
+    }
+
+    #[test]
+    fn broken_images_are_saved_as_they_were() {
+        let line = r#"{"Image":{"id":3,"layer":1,"blob":"gone","position":[5.0,6.0],"scale":[0.5,2.0],"rotation":1.0,"kind":{"Local":"/pictures/cat.png"}}}"#;
+        let Ok(Item::Image(saved)) = serde_json::from_str::<Item>(line) else {
+            panic!("couldnt read the image");
+        };
+
+        let mut image = ItemImage::placeholder(saved.kind.argument());
+        image.blob = saved.blob.clone();
+        image.failed = Some("missing".to_owned());
+        let image = Item::Image(image)
+            .with_id(saved.id)
+            .with_layer(saved.layer)
+            .with_position(saved.position)
+            .with_scale(saved.scale)
+            .with_rotation(saved.rotation);
+
+        assert_eq!(serde_json::to_string(&image).unwrap(), line);
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use twox_hash::XxHash3_128;
+
+use super::sniff;
+use super::store;
+
+/// cached images stored by a hash of their contents, so identical images are only kept once
+/// and different images with the same name dont overwrite each other
+pub struct Cache {
+    /// directory path with the blobs, named by their hash
+    pub dir: PathBuf,
+    /// where each blob came from (url or path), so its not fetched again
+    sources: HashMap<String, String>,
+    /// how many items use each blob, including ones only the history holds
+    /// rebuilt from the items on load rather than saved
+    refs: HashMap<String, usize>,
+    /// blobs cached since the last [`Cache::take_fresh`], whose thumbnails are still to be made
+    fresh: Vec<String>,
+}
+
+/// longest sides of the thumbnails made for images bigger than them, smallest first
+pub const TIERS: [u32; 2] = [256, 1024];
+
+impl Cache {
+    const INDEX: &str = "index.store";
+
+    pub fn open(dir: PathBuf) -> Self {
+        let sources = std::fs::read_to_string(dir.join(Self::INDEX))
+            .map_err(|e| e.to_string())
+            .and_then(|s| serde_json::from_str(&s).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                println!("couldnt read the cache index, starting a new one: {e}");
+                HashMap::new()
+            });
+
+        Self {
+            dir,
+            sources,
+            refs: HashMap::new(),
+            fresh: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn hash(bytes: &[u8]) -> String {
+        format!("{:032x}", XxHash3_128::oneshot(bytes))
+    }
+
+    #[inline]
+    pub fn path(&self, blob: &str) -> PathBuf {
+        self.dir.join(blob)
+    }
+
+    /// where the thumbnail of `blob` with the longest side `tier` is kept
+    #[inline]
+    pub fn thumbnail_path(&self, blob: &str, tier: u32) -> PathBuf {
+        thumbnail_in(&self.dir, blob, tier)
+    }
+
+    #[inline]
+    pub fn contains(&self, blob: &str) -> bool {
+        !blob.is_empty() && self.path(blob).exists()
+    }
+
+    /// the blob already fetched from `source`, if its still around
+    pub fn lookup(&self, source: &str) -> Option<&str> {
+        self.sources
+            .get(source)
+            .map(String::as_str)
+            .filter(|b| self.contains(b))
+    }
+
+    /// stores `bytes` fetched from `source`, giving back its blob
+    pub fn insert(&mut self, source: &str, bytes: &[u8]) -> std::io::Result<String> {
+        let blob = Self::hash(bytes);
+        if !self.contains(&blob) {
+            store::write_atomic(self.path(&blob), bytes)?;
+            self.fresh.push(blob.clone());
+        }
+
+        if self.sources.get(source) != Some(&blob) {
+            self.sources.insert(source.to_owned(), blob.clone());
+            self.save_index()?;
+        }
+        Ok(blob)
+    }
+
+    /// the blobs cached since this was last called, so their thumbnails can be made in the background
+    pub fn take_fresh(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.fresh)
+    }
+
+    fn save_index(&self) -> std::io::Result<()> {
+        store::write_atomic(
+            self.dir.join(Self::INDEX),
+            serde_json::to_string(&self.sources)?.as_bytes(),
+        )
+    }
+
+    /// sets the reference counts to how many of `blobs` use each blob
+    pub fn recount<'a>(&mut self, blobs: impl Iterator<Item = &'a str>) {
+        self.refs.clear();
+        for blob in blobs.filter(|b| !b.is_empty()) {
+            *self.refs.entry(blob.to_owned()).or_default() += 1;
+        }
+    }
+
+    pub fn retain(&mut self, blob: &str) {
+        *self.refs.entry(blob.to_owned()).or_default() += 1;
+    }
+
+    /// removes the blob once nothing uses it anymore
+    pub fn release(&mut self, blob: &str) -> std::io::Result<()> {
+        if let Some(count) = self.refs.get_mut(blob) {
+            *count = count.saturating_sub(1);
+            if *count != 0 {
+                return Ok(());
+            }
+            self.refs.remove(blob);
+        }
+
+        self.remove_unused(blob)
+    }
+
+    /// removes the blob if nothing uses it
+    pub fn remove_unused(&mut self, blob: &str) -> std::io::Result<()> {
+        if self.refs.contains_key(blob) || !self.contains(blob) {
+            return Ok(());
+        }
+
+        std::fs::remove_file(self.path(blob))?;
+        for tier in TIERS {
+            _ = std::fs::remove_file(self.thumbnail_path(blob, tier));
+        }
+        let before = self.sources.len();
+        self.sources.retain(|_, b| b != blob);
+        if self.sources.len() != before {
+            self.save_index()?;
+        }
+        Ok(())
+    }
+
+    /// removes every file in the cache that nothing uses (or that isnt a blob or thumbnail), other than `keep`
+    /// returns how many were removed
+    pub fn collect_garbage(&mut self, keep: &[&str]) -> std::io::Result<usize> {
+        let mut removed = 0;
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            // thumbnails go with their blob
+            let blob = name.split_once('.').map_or(name, |(blob, _)| blob);
+            if name == Self::INDEX || self.refs.contains_key(blob) || keep.contains(&blob) {
+                continue;
+            }
+
+            std::fs::remove_file(&path)?;
+            removed += 1;
+        }
+
+        let dir = &self.dir;
+        self.sources.retain(|_, b| dir.join(&*b).exists());
+        self.save_index()?;
+        Ok(removed)
+    }
+
+    /// the blob for a file named `name` cached before blobs existed, moving it into a blob
+    pub fn adopt(&mut self, source: &str, name: &str) -> std::io::Result<Option<String>> {
+        let path = self.dir.join(name);
+        if name.is_empty() || !path.is_file() {
+            return Ok(None);
+        }
+
+        let blob = self.insert(source, &std::fs::read(&path)?)?;
+        if blob != name {
+            std::fs::remove_file(path)?;
+        }
+        Ok(Some(blob))
+    }
+}
+
+#[inline]
+fn thumbnail_in(dir: &Path, blob: &str, tier: u32) -> PathBuf {
+    dir.join(format!("{blob}.{tier}"))
+}
+
+/// makes whichever thumbnails of `blob` in `dir` are missing
+/// this is slow, so the [`super::fetch::Fetcher`] does it in the background for newly cached images
+pub fn make_thumbnails(dir: &Path, blob: &str) -> image::ImageResult<()> {
+    make_thumbnails_of(dir, blob, &std::fs::read(dir.join(blob))?)
+}
+
+/// makes whichever thumbnails of the image `bytes` (stored as `blob` in `dir`) are missing
+/// images no bigger than a tier dont get a thumbnail for it, and svgs and animations dont get any
+fn make_thumbnails_of(dir: &Path, blob: &str, bytes: &[u8]) -> image::ImageResult<()> {
+    let missing = |side| {
+        TIERS
+            .into_iter()
+            .filter(move |&t| t < side && !thumbnail_in(dir, blob, t).exists())
+    };
+    let Some(side) = thumbnail_size(bytes).map(|(w, h)| w.max(h)) else {
+        return Ok(());
+    };
+    if missing(side).next().is_none() {
+        return Ok(());
+    }
+
+    let image = image::load_from_memory(bytes)?;
+    for tier in missing(side) {
+        let mut png = std::io::Cursor::new(Vec::new());
+        image
+            .resize(tier, tier, image::imageops::FilterType::Triangle)
+            .write_to(&mut png, image::ImageFormat::Png)?;
+        store::write_atomic(thumbnail_in(dir, blob, tier), png.get_ref())?;
+    }
+    Ok(())
+}
+
+/// the pixels of `blob` in the cache at `dir`, as thumbnail `tier` or the full image for [`None`]
+/// making its thumbnails first if theyre missing (for images cached before thumbnails, or not made yet)
+/// this is slow, so the [`super::fetch::Fetcher`] does it in the background
+pub fn decode(dir: &Path, blob: &str, tier: Option<u32>) -> image::ImageResult<image::RgbaImage> {
+    let full = || std::fs::read(dir.join(blob));
+    let Some(tier) = tier else {
+        return Ok(image::load_from_memory(&full()?)?.into_rgba8());
+    };
+
+    let path = thumbnail_in(dir, blob, tier);
+    if !path.exists() {
+        make_thumbnails_of(dir, blob, &full()?)?;
+    }
+    Ok(image::load_from_memory(&std::fs::read(path)?)?.into_rgba8())
+}
+
+/// the thumbnail tier to show an image with the longest side `full` at when its `on_screen` pixels big
+/// [`None`] meaning the full image
+pub fn tier(on_screen: f32, full: u32) -> Option<u32> {
+    TIERS
+        .into_iter()
+        .find(|&t| t < full && on_screen <= t as f32)
+}
+
+/// the size of the image `bytes` if its a still image that thumbnails could be made of
+pub fn thumbnail_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let animated = bytes.starts_with(b"GIF8")
+        || (bytes.starts_with(b"RIFF") && bytes.windows(4).take(64).any(|w| w == b"ANIM"))
+        || (bytes.starts_with(b"\x89PNG") && bytes.windows(4).take(4096).any(|w| w == b"acTL"));
+    if animated || sniff::is_svg(bytes) {
+        return None;
+    }
+
+    let (width, height) = image::io::Reader::new(std::io::Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()?;
+    Some((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{decode, tier, Cache};
+    use crate::board::fetch::Fetcher;
+
+    #[test]
+    fn blobs_are_shared_and_removed_when_unused() {
+        let dir = std::env::temp_dir().join(format!("board-cache-test-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut cache = Cache::open(dir.clone());
+
+        // two sources with the same contents share a blob
+        let a = cache.insert("https://a.com/image.png", b"same").unwrap();
+        let b = cache.insert("https://b.com/image.png", b"same").unwrap();
+        let c = cache
+            .insert("https://c.com/image.png", b"different")
+            .unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(cache.lookup("https://b.com/image.png"), Some(a.as_str()));
+
+        cache.recount([a.as_str(), b.as_str()].into_iter());
+        cache.release(&a).unwrap();
+        assert!(cache.contains(&a));
+        cache.release(&a).unwrap();
+        assert!(!cache.contains(&a));
+        assert_eq!(cache.lookup("https://a.com/image.png"), None);
+
+        // files cached before blobs existed get moved into one
+        std::fs::write(dir.join("old.png"), b"old").unwrap();
+        let old = cache
+            .adopt("/pictures/old.png", "old.png")
+            .unwrap()
+            .unwrap();
+        assert!(cache.contains(&old) && !dir.join("old.png").exists());
+
+        assert_eq!(cache.collect_garbage(&[&old]).unwrap(), 1);
+        assert!(!cache.contains(&c) && cache.contains(&old));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn thumbnails_are_made_for_big_images() {
+        let dir = std::env::temp_dir().join(format!("board-thumbnail-test-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut cache = Cache::open(dir.clone());
+
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(600, 300)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let blob = cache.insert("/pictures/wide.png", png.get_ref()).unwrap();
+
+        // made in the background once its cached, and only smaller than the image
+        let fetcher = Fetcher::new();
+        assert_eq!(cache.take_fresh(), [blob.as_str()]);
+        fetcher.make_thumbnails(&dir, &blob);
+        let start = Instant::now();
+        while !cache.thumbnail_path(&blob, 256).exists()
+            && start.elapsed() < Duration::from_secs(10)
+        {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let small =
+            image::load_from_memory(&std::fs::read(cache.thumbnail_path(&blob, 256)).unwrap())
+                .unwrap();
+        assert_eq!((small.width(), small.height()), (256, 128));
+        assert!(!cache.thumbnail_path(&blob, 1024).exists());
+        assert_eq!(decode(&dir, &blob, Some(256)).unwrap().width(), 256);
+        assert_eq!(decode(&dir, &blob, None).unwrap().width(), 600);
+
+        // thumbnails are kept and removed with their blob
+        cache.recount([blob.as_str()].into_iter());
+        assert_eq!(cache.collect_garbage(&[]).unwrap(), 0);
+        cache.release(&blob).unwrap();
+        assert!(!cache.thumbnail_path(&blob, 256).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(tier(100.0, 600), Some(256));
+        assert_eq!(tier(300.0, 600), None);
+        assert_eq!(tier(300.0, 4000), Some(1024));
+        assert_eq!(tier(2000.0, 4000), None);
+        // no thumbnail is bigger than the image
+        assert_eq!(tier(100.0, 200), None);
+    }
+}
@@ -0,0 +1,232 @@
+use ggez::{
+    graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, Rect, Text},
+    Context,
+};
+
+use super::board::ItemImage;
+use super::fetch::{Fetcher, Purpose};
+
+/// images scraped from a web page which the user can pick from
+#[derive(Default)]
+pub struct Choices {
+    /// (loaded image, url)
+    // TODO: prob should just use `Image` instead of `ItemImage`
+    list: Vec<(Option<ItemImage>, String)>,
+    /// index into `list` of the highlighted choice
+    highlighted: usize,
+}
+
+impl Choices {
+    /// how many choices are shown (and loaded) at a time
+    pub const CHOICE_AMOUNT: usize = 4;
+    /// gap between thumbnails in pixels
+    const PADDING: f32 = 20.0;
+
+    #[inline]
+    pub fn push(&mut self, url: String) {
+        self.list.push((None, url))
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    #[inline]
+    pub fn page(&self) -> usize {
+        self.highlighted / Self::CHOICE_AMOUNT
+    }
+
+    #[inline]
+    pub fn page_count(&self) -> usize {
+        self.list.len().div_ceil(Self::CHOICE_AMOUNT)
+    }
+
+    /// range of indices into `list` on the current page
+    fn page_range(&self) -> std::ops::Range<usize> {
+        let start = self.page() * Self::CHOICE_AMOUNT;
+        start..(start + Self::CHOICE_AMOUNT).min(self.list.len())
+    }
+
+    /// starts downloading the images on the current page
+    pub fn load_page(&mut self, fetcher: &mut Fetcher) {
+        for i in self.page_range() {
+            if self.list[i].0.is_none() {
+                fetcher.request(&self.list[i].1, Purpose::Choice);
+            }
+        }
+    }
+
+    /// sets the image of the choice with `url` once its downloaded, dropping it if that failed
+    pub fn loaded(&mut self, url: &str, image: Option<ItemImage>) {
+        let Some(i) = self.list.iter().position(|c| c.1 == url) else {
+            return;
+        };
+
+        match image {
+            Some(image) => self.list[i].0 = Some(image),
+            None => {
+                self.list.remove(i);
+                self.highlighted = self.highlighted.min(self.list.len().saturating_sub(1));
+            }
+        }
+    }
+
+    /// moves the highlight by `by` choices, wrapping around the ends
+    pub fn move_highlight(&mut self, by: isize) {
+        if self.list.is_empty() {
+            return;
+        }
+
+        self.highlighted =
+            (self.highlighted as isize + by).rem_euclid(self.list.len() as isize) as usize;
+    }
+
+    /// moves the highlight to the same slot `by` pages over
+    pub fn move_page(&mut self, by: isize) {
+        if self.list.is_empty() {
+            return;
+        }
+
+        let slot = self.highlighted % Self::CHOICE_AMOUNT;
+        let page = (self.page() as isize + by).rem_euclid(self.page_count() as isize) as usize;
+        self.highlighted = (page * Self::CHOICE_AMOUNT + slot).min(self.list.len() - 1);
+    }
+
+    pub fn set_highlight(&mut self, i: usize) {
+        if i < self.list.len() {
+            self.highlighted = i;
+        }
+    }
+
+    /// removes the highlighted choice if it has been loaded
+    pub fn take_highlighted(&mut self) -> Option<ItemImage> {
+        if !matches!(self.list.get(self.highlighted), Some((Some(_), _))) {
+            return None;
+        }
+
+        let chosen = self.list.remove(self.highlighted).0;
+        self.highlighted = self.highlighted.min(self.list.len().saturating_sub(1));
+        chosen
+    }
+
+    /// empties the list, giving back the blobs of the choices that were loaded (and therefore cached)
+    pub fn clear(&mut self) -> Vec<String> {
+        self.highlighted = 0;
+        self.list
+            .drain(..)
+            .filter_map(|c| c.0.map(|i| i.blob))
+            .collect()
+    }
+
+    /// blobs of the choices that were loaded
+    pub fn blobs(&self) -> Vec<&str> {
+        self.list
+            .iter()
+            .filter_map(|c| c.0.as_ref().map(|i| i.blob.as_str()))
+            .collect()
+    }
+
+    /// length of a thumbnails side in pixels
+    #[inline]
+    fn slot_size(resolution: (f32, f32)) -> f32 {
+        ((resolution.0 - Self::PADDING) / Self::CHOICE_AMOUNT as f32 - Self::PADDING)
+            .min(resolution.1 / 2.0)
+            .min(200.0)
+    }
+
+    /// screen rect of the `slot`th thumbnail on a page
+    fn slot_rect(slot: usize, resolution: (f32, f32)) -> Rect {
+        let size = Self::slot_size(resolution);
+        let width = Self::CHOICE_AMOUNT as f32 * (size + Self::PADDING) - Self::PADDING;
+
+        Rect::new(
+            (resolution.0 - width) / 2.0 + slot as f32 * (size + Self::PADDING),
+            (resolution.1 - size) / 2.0,
+            size,
+            size,
+        )
+    }
+
+    /// index of the choice whose thumbnail is under `pos` (screen coords)
+    pub fn choice_at(&self, pos: (f32, f32), resolution: (f32, f32)) -> Option<usize> {
+        self.page_range()
+            .find(|i| Self::slot_rect(i % Self::CHOICE_AMOUNT, resolution).contains([pos.0, pos.1]))
+    }
+
+    /// draws the current page as a strip of thumbnails in the middle of the screen
+    /// `colours`: (background, text)
+    pub fn draw(
+        &self,
+        c: &mut Canvas,
+        cc: &Context,
+        resolution: (f32, f32),
+        colours: (Color, Color),
+    ) {
+        if self.list.is_empty() {
+            return;
+        }
+
+        for i in self.page_range() {
+            let slot = Self::slot_rect(i % Self::CHOICE_AMOUNT, resolution);
+
+            c.draw(
+                &Mesh::new_rectangle(cc, DrawMode::fill(), slot, colours.0)
+                    .expect("couldnt make the choice background"),
+                DrawParam::new(),
+            );
+
+            if let Some(image) = &self.list[i].0 {
+                let Some(handle) = image.handle() else {
+                    continue;
+                };
+                let scale = (slot.w / handle.width() as f32).min(slot.h / handle.height() as f32);
+                let size = (
+                    handle.width() as f32 * scale,
+                    handle.height() as f32 * scale,
+                );
+
+                c.draw(
+                    handle,
+                    DrawParam::new()
+                        .dest([
+                            slot.x + (slot.w - size.0) / 2.0,
+                            slot.y + (slot.h - size.1) / 2.0,
+                        ])
+                        .scale([scale, scale]),
+                );
+            } else {
+                c.draw(
+                    Text::new(super::board::Board::name_from_path(&self.list[i].1))
+                        .set_bounds([slot.w, slot.h])
+                        .set_scale(15.0),
+                    DrawParam::new().dest([slot.x, slot.y]).color(colours.1),
+                );
+            }
+
+            c.draw(
+                &Mesh::new_rectangle(
+                    cc,
+                    DrawMode::stroke(if i == self.highlighted { 4.0 } else { 1.0 }),
+                    slot,
+                    colours.1,
+                )
+                .expect("couldnt make the choice outline"),
+                DrawParam::new(),
+            );
+        }
+
+        let first = Self::slot_rect(0, resolution);
+        c.draw(
+            Text::new(format!(
+                "{}/{}   Left/Right browse, Up/Down page, Enter pick, Esc cancel",
+                self.page() + 1,
+                self.page_count()
+            ))
+            .set_scale(20.0),
+            DrawParam::new()
+                .dest([first.x, first.y + first.h + Self::PADDING])
+                .color(colours.1),
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use ggez::graphics::{Canvas, Color, DrawParam, MeshBuilder, Text};
+use ggez::Context;
+use serde::{Deserialize, Serialize};
+
+use super::board::ItemId;
+use super::geometry::OrientedRect;
+use super::layer::LayerId;
+use super::shape;
+use super::style::ShapeStyle;
+use crate::camera::Camera;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Routing {
+    #[default]
+    Straight,
+    Curved,
+    /// horizontal, vertical then horizontal again
+    Elbow,
+}
+
+impl Routing {
+    pub fn next(self) -> Self {
+        match self {
+            Routing::Straight => Routing::Curved,
+            Routing::Curved => Routing::Elbow,
+            Routing::Elbow => Routing::Straight,
+        }
+    }
+
+    /// points in world coords going from the edge of `from` to the edge of `to`
+    pub fn route(self, from: OrientedRect, to: OrientedRect) -> Vec<(f32, f32)> {
+        let a = from.edge_towards(to.centre());
+        let b = to.edge_towards(from.centre());
+
+        match self {
+            Routing::Straight => vec![a, b],
+            Routing::Elbow => {
+                let x = (a.0 + b.0) / 2.0;
+                vec![a, (x, a.1), (x, b.1), b]
+            }
+            Routing::Curved => {
+                // cubic bezier leaving and arriving horizontally
+                let pull = (b.0 - a.0) / 2.0;
+                let (c1, c2) = ((a.0 + pull, a.1), (b.0 - pull, b.1));
+
+                (0..=Self::CURVE_POINTS)
+                    .map(|i| {
+                        let t = i as f32 / Self::CURVE_POINTS as f32;
+                        let u = 1.0 - t;
+                        let w = [u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t];
+                        (
+                            w[0] * a.0 + w[1] * c1.0 + w[2] * c2.0 + w[3] * b.0,
+                            w[0] * a.1 + w[1] * c1.1 + w[2] * c2.1 + w[3] * b.1,
+                        )
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    const CURVE_POINTS: usize = 24;
+}
+
+/// line between two items which follows them around
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ItemConnector {
+    pub id: ItemId,
+    #[serde(default)]
+    pub layer: LayerId,
+    pub from: ItemId,
+    pub to: ItemId,
+    pub routing: Routing,
+    pub label: Option<String>,
+    pub style: ShapeStyle,
+
+    /// in world coords, empty if either end is missing
+    #[serde(skip)]
+    pub route: Vec<(f32, f32)>,
+    /// while the label is being typed into a caret is drawn after it
+    #[serde(skip)]
+    pub editing: bool,
+}
+
+impl ItemConnector {
+    /// world size of label text
+    const LABEL_SCALE: f32 = 32.0;
+
+    pub fn new(from: ItemId, to: ItemId) -> Self {
+        Self {
+            id: 0,
+            layer: 0,
+            from,
+            to,
+            routing: Routing::default(),
+            label: None,
+            style: ShapeStyle::default(),
+            route: Vec::new(),
+            editing: false,
+        }
+    }
+
+    #[inline]
+    pub fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or_default()
+    }
+
+    /// an empty label removes it, returns the old label
+    pub fn set_label(&mut self, label: String) -> String {
+        let old = self.label.take().unwrap_or_default();
+        self.label = (!label.is_empty()).then_some(label);
+        old
+    }
+
+    /// box around the route, padded so thin routes can still be clicked
+    /// straight routes get a box along them so they dont cover everything around them
+    pub fn oriented(&self) -> OrientedRect {
+        let pad = self.style.width.max(12.0);
+        let Some(&first) = self.route.first() else {
+            return OrientedRect::new((0.0, 0.0, 0.0, 0.0), 0.0);
+        };
+
+        if let [a, b] = self.route[..] {
+            let d = (b.0 - a.0, b.1 - a.1);
+            let length = d.0.hypot(d.1);
+            let centre = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+            return OrientedRect::new(
+                (centre.0 - length / 2.0, centre.1 - pad, length, pad * 2.0),
+                d.1.atan2(d.0),
+            );
+        }
+
+        let (min, max) = self.route.iter().fold((first, first), |(min, max), p| {
+            (
+                (min.0.min(p.0), min.1.min(p.1)),
+                (max.0.max(p.0), max.1.max(p.1)),
+            )
+        });
+
+        OrientedRect::new(
+            (
+                min.0 - pad,
+                min.1 - pad,
+                max.0 - min.0 + pad * 2.0,
+                max.1 - min.1 + pad * 2.0,
+            ),
+            0.0,
+        )
+    }
+
+    /// world position halfway along the route
+    fn midpoint(&self) -> (f32, f32) {
+        let length = |w: &[(f32, f32)]| (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1);
+        let mut left = self.route.windows(2).map(length).sum::<f32>() / 2.0;
+
+        for w in self.route.windows(2) {
+            let l = length(w);
+            if left <= l && l > 0.0 {
+                let t = left / l;
+                return (
+                    w[0].0 + (w[1].0 - w[0].0) * t,
+                    w[0].1 + (w[1].1 - w[0].1) * t,
+                );
+            }
+            left -= l;
+        }
+
+        self.route.first().copied().unwrap_or_default()
+    }
+
+    /// `text` being the boards text colour, which unstyled connectors use
+    pub fn draw(&self, cam: Camera, c: &mut Canvas, cc: &Context, text: Color) {
+        if self.route.len() < 2 {
+            return;
+        }
+        let colour = self.style.stroke_colour(text);
+        let points = self
+            .route
+            .iter()
+            .map(|&p| {
+                let p = cam.world_to_screen(p);
+                [p.0, p.1]
+            })
+            .collect::<Vec<[f32; 2]>>();
+
+        let mut mesh = MeshBuilder::new();
+        _ = mesh.line(&points, self.style.width * cam.zoom, colour);
+        shape::arrowhead(
+            &mut mesh,
+            points[points.len() - 1],
+            points[points.len() - 2],
+            self.style.width * cam.zoom,
+            colour,
+        );
+        shape::draw_mesh(c, cc, &mesh);
+
+        // blinks every half a second like the text caret
+        let caret =
+            self.editing && (cc.time.time_since_start().as_millis() / 500).is_multiple_of(2);
+        if self.label.is_some() || caret {
+            let label = Text::new(format!("{}{}", self.label(), if caret { "|" } else { "" }))
+                .set_scale(Self::LABEL_SCALE * cam.zoom)
+                .clone();
+            let size = label.measure(cc).unwrap();
+            let mid = cam.world_to_screen(self.midpoint());
+
+            c.draw(
+                &label,
+                DrawParam::new()
+                    .dest([mid.0 - size.x / 2.0, mid.1 - size.y / 2.0])
+                    .color(colour),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ItemConnector, Routing};
+    use crate::board::geometry::OrientedRect;
+
+    #[test]
+    fn routes_go_between_the_edges() {
+        let a = OrientedRect::new((0.0, 0.0, 20.0, 20.0), 0.0);
+        let b = OrientedRect::new((100.0, 40.0, 20.0, 20.0), 0.0);
+
+        for routing in [Routing::Straight, Routing::Curved, Routing::Elbow] {
+            let route = routing.route(a, b);
+            assert_eq!(route.first(), Some(&a.edge_towards(b.centre())));
+            assert_eq!(route.last(), Some(&b.edge_towards(a.centre())));
+        }
+        assert_eq!(
+            Routing::Elbow.route(a, b),
+            [(20.0, 14.0), (60.0, 14.0), (60.0, 46.0), (100.0, 46.0)]
+        );
+    }
+
+    #[test]
+    fn labels_sit_halfway_along() {
+        let mut connector = ItemConnector::new(1, 2);
+        connector.route = vec![(0.0, 0.0), (10.0, 0.0), (10.0, 30.0)];
+        assert_eq!(connector.midpoint(), (10.0, 10.0));
+        assert!(connector.oriented().contains((0.0, 30.0)));
+
+        connector.route.pop();
+        // a straight route only covers whats along it
+        assert!(!connector.oriented().contains((0.0, 30.0)));
+        assert!(connector.oriented().contains((5.0, 3.0)));
+
+        assert_eq!(connector.set_label("hi".to_owned()), "");
+        assert_eq!(connector.set_label(String::new()), "hi");
+        assert_eq!(connector.label, None);
+    }
+}
@@ -0,0 +1,197 @@
+/// edit buffer and caret for a text item being typed into
+pub struct TextEditor {
+    /// index of the item being edited
+    item: usize,
+    /// text before editing started
+    original: String,
+    buffer: String,
+    /// byte index into `buffer`, always on a char boundary
+    caret: usize,
+}
+
+impl TextEditor {
+    /// starts with the caret at the end of `text`
+    pub fn new(item: usize, text: String) -> Self {
+        Self {
+            item,
+            caret: text.len(),
+            buffer: text.clone(),
+            original: text,
+        }
+    }
+
+    #[inline]
+    pub fn item(&self) -> usize {
+        self.item
+    }
+
+    /// the item at `i` was removed (without stopping editing), so the one being edited moved back if it was after it
+    #[inline]
+    pub fn removed(&mut self, i: usize) {
+        if self.item > i {
+            self.item -= 1;
+        }
+    }
+
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.buffer
+    }
+
+    /// (item, text before editing, text after editing)
+    pub fn finish(self) -> (usize, String, String) {
+        (self.item, self.original, self.buffer)
+    }
+
+    pub fn insert(&mut self, s: &str) {
+        // no carriage returns so that lines stay simple to find
+        let s = s.replace("\r\n", "\n").replace('\r', "\n");
+        self.buffer.insert_str(self.caret, &s);
+        self.caret += s.len();
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(c) = self.buffer[..self.caret].chars().next_back() {
+            self.caret -= c.len_utf8();
+            self.buffer.remove(self.caret);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.caret < self.buffer.len() {
+            self.buffer.remove(self.caret);
+        }
+    }
+
+    pub fn left(&mut self) {
+        if let Some(c) = self.buffer[..self.caret].chars().next_back() {
+            self.caret -= c.len_utf8();
+        }
+    }
+
+    pub fn right(&mut self) {
+        if let Some(c) = self.buffer[self.caret..].chars().next() {
+            self.caret += c.len_utf8();
+        }
+    }
+
+    /// start of the current line
+    pub fn home(&mut self) {
+        self.caret = self.line_start(self.caret);
+    }
+
+    /// end of the current line
+    pub fn end(&mut self) {
+        self.caret = self.buffer[self.caret..]
+            .find('\n')
+            .map_or(self.buffer.len(), |i| self.caret + i);
+    }
+
+    /// same column on the line above (or as close as it can get)
+    pub fn up(&mut self) {
+        let start = self.line_start(self.caret);
+        if start == 0 {
+            self.caret = 0;
+            return;
+        }
+
+        let column = self.buffer[start..self.caret].chars().count();
+        self.caret = self.line_start(start - 1);
+        self.move_to_column(column);
+    }
+
+    /// same column on the line below (or as close as it can get)
+    pub fn down(&mut self) {
+        let column = self.buffer[self.line_start(self.caret)..self.caret]
+            .chars()
+            .count();
+        let Some(next) = self.buffer[self.caret..].find('\n') else {
+            self.caret = self.buffer.len();
+            return;
+        };
+
+        self.caret += next + 1;
+        self.move_to_column(column);
+    }
+
+    /// byte index of the start of the line holding `i`
+    fn line_start(&self, i: usize) -> usize {
+        self.buffer[..i].rfind('\n').map_or(0, |n| n + 1)
+    }
+
+    /// moves right from the start of a line by up to `column` chars without leaving the line
+    fn move_to_column(&mut self, column: usize) {
+        self.caret += self.buffer[self.caret..]
+            .chars()
+            .take_while(|&c| c != '\n')
+            .take(column)
+            .map(char::len_utf8)
+            .sum::<usize>();
+    }
+
+    /// (everything before the caret, the rest of the caret's line)
+    pub fn split_at_caret(&self) -> (&str, &str) {
+        let rest = &self.buffer[self.caret..];
+        (
+            &self.buffer[..self.caret],
+            &rest[..rest.find('\n').unwrap_or(rest.len())],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TextEditor;
+
+    fn editor(text: &str) -> TextEditor {
+        TextEditor::new(0, text.to_owned())
+    }
+
+    #[test]
+    fn typing_and_deleting() {
+        let mut e = editor("héllo");
+        e.backspace();
+        e.insert("p\r\nme");
+        assert_eq!(e.text(), "héllp\nme");
+
+        e.home();
+        e.delete();
+        e.up();
+        e.right();
+        e.backspace();
+        e.backspace();
+        assert_eq!(e.text(), "éllp\ne");
+        e.backspace();
+        assert_eq!(e.text(), "éllp\ne");
+
+        let (item, original, text) = e.finish();
+        assert_eq!(
+            (item, original.as_str(), text.as_str()),
+            (0, "héllo", "éllp\ne")
+        );
+    }
+
+    #[test]
+    fn moving_between_lines_keeps_the_column() {
+        let mut e = editor("first line\nab\nthird line");
+        e.up();
+        e.up();
+        e.end();
+        assert_eq!(e.split_at_caret(), ("first line", ""));
+
+        e.left();
+        e.left();
+        e.down();
+        assert_eq!(e.split_at_caret(), ("first line\nab", ""));
+        e.down();
+        assert_eq!(e.split_at_caret(), ("first line\nab\nth", "ird line"));
+
+        e.down();
+        assert_eq!(e.split_at_caret(), ("first line\nab\nthird line", ""));
+        e.home();
+        e.up();
+        e.up();
+        e.up();
+        assert_eq!(e.split_at_caret(), ("", "first line"));
+    }
+}
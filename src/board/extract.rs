@@ -0,0 +1,161 @@
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+
+/// images declared smaller than this (in pixels) are left out, theyre usually icons or trackers
+const MIN_SIZE: u32 = 16;
+
+/// attributes lazy loading scripts keep the real image in, before `src` which is often a blank one
+const SOURCES: [&str; 4] = ["data-src", "data-lazy-src", "data-original", "src"];
+const SRCSETS: [&str; 2] = ["data-srcset", "srcset"];
+
+/// an image found on a page, with its declared width if it has one
+struct Candidate {
+    url: Url,
+    width: Option<u32>,
+}
+
+/// the urls of the images on the web page `html` which came from `page`
+/// relative urls are resolved against the page (or its `<base>`), the biggest declared images come first
+pub fn images(html: &str, page: &str) -> Vec<String> {
+    let Ok(page) = Url::parse(page) else {
+        return Vec::new();
+    };
+    let html = Html::parse_document(html);
+    let select = |s: &str| Selector::parse(s).expect("selectors are valid");
+
+    let base = html
+        .select(&select("base[href]"))
+        .next()
+        .and_then(|b| page.join(b.value().attr("href")?).ok())
+        .unwrap_or(page);
+    let resolve = |url: &str| {
+        base.join(url.trim())
+            .ok()
+            .filter(|u| matches!(u.scheme(), "http" | "https"))
+    };
+
+    let mut candidates = Vec::new();
+
+    // the image a page wants shown when its shared, with its size in other meta tags
+    let meta = |property: &str| {
+        html.select(&select(&format!(
+            "meta[property='{property}'], meta[name='{property}']"
+        )))
+        .find_map(|m| m.value().attr("content"))
+    };
+    if let Some(url) = meta("og:image")
+        .or_else(|| meta("twitter:image"))
+        .and_then(resolve)
+    {
+        let width = meta("og:image:width").and_then(|w| w.trim().parse().ok());
+        candidates.push(Candidate { url, width });
+    }
+
+    for element in html.select(&select("img, picture source")) {
+        let declared = dimension(element, "width");
+        if declared.is_some_and(|w| w < MIN_SIZE)
+            || dimension(element, "height").is_some_and(|h| h < MIN_SIZE)
+        {
+            continue;
+        }
+
+        // the biggest image in the srcset, otherwise whatever source is there
+        let from_srcset = SRCSETS
+            .iter()
+            .find_map(|&a| element.value().attr(a))
+            .and_then(|s| largest_in_srcset(s, declared))
+            .and_then(|(url, width)| Some((resolve(url)?, width)));
+        let candidate = from_srcset.or_else(|| {
+            let url = SOURCES
+                .iter()
+                .filter_map(|&a| element.value().attr(a))
+                .find(|s| !s.trim().is_empty() && !s.starts_with("data:"))?;
+            Some((resolve(url)?, declared))
+        });
+
+        if let Some((url, width)) = candidate {
+            candidates.push(Candidate { url, width });
+        }
+    }
+
+    // biggest first, keeping the page order otherwise with undeclared sizes last
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.width));
+
+    let mut urls: Vec<String> = Vec::with_capacity(candidates.len());
+    for candidate in candidates {
+        let url = String::from(candidate.url);
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    urls
+}
+
+/// a size attribute in pixels, ignoring percentages and the like
+fn dimension(element: ElementRef, attribute: &str) -> Option<u32> {
+    let value = element.value().attr(attribute)?.trim();
+    value.strip_suffix("px").unwrap_or(value).parse().ok()
+}
+
+/// the url of the biggest image in a `srcset` with its width if thats known
+/// `declared` being the width of the element for working out `x` descriptors
+fn largest_in_srcset(srcset: &str, declared: Option<u32>) -> Option<(&str, Option<u32>)> {
+    srcset
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split_whitespace();
+            let url = parts.next().filter(|u| !u.starts_with("data:"))?;
+            let descriptor = parts.next().unwrap_or("1x");
+
+            // (width, density) so widths are compared first when theres both
+            let size = if let Some(w) = descriptor.strip_suffix('w') {
+                (w.parse::<u32>().ok(), 0.0)
+            } else {
+                let x = descriptor.strip_suffix('x')?.parse::<f32>().ok()?;
+                (declared.map(|d| (d as f32 * x) as u32), x)
+            };
+            Some((url, size))
+        })
+        .max_by(|(_, a), (_, b)| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)))
+        .map(|(url, (width, _))| (url, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::images;
+
+    #[test]
+    fn article_images_are_found_and_ranked() {
+        let html = include_str!("fixtures/article.html");
+        assert_eq!(
+            images(html, "https://news.example.com/2024/story.html"),
+            [
+                "https://cdn.example.com/hero-1600.jpg",
+                "https://news.example.com/media/lazy.png",
+                "https://news.example.com/2024/chart.webp",
+                "https://news.example.com/2024/chart.png",
+                "https://cdn.example.com/og.jpg",
+                "https://news.example.com/media/inline.gif",
+            ]
+        );
+    }
+
+    #[test]
+    fn base_and_srcset_densities_are_used() {
+        let html = include_str!("fixtures/gallery.html");
+        assert_eq!(
+            images(html, "https://gallery.example.org/view?id=3"),
+            [
+                "https://static.example.org/photos/cat@3x.jpg",
+                "https://static.example.org/photos/dog.jpg",
+                "http://other.example.net/bird.png",
+            ]
+        );
+    }
+
+    #[test]
+    fn pages_without_images_give_nothing() {
+        assert!(images("<p>nothing to see</p>", "https://example.com").is_empty());
+        assert!(images("<img src='/a.png'>", "not a url").is_empty());
+    }
+}
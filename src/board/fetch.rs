@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::board::ItemId;
+use super::cache;
+
+/// what a download is for
+#[derive(Clone, Debug, PartialEq)]
+pub enum Purpose {
+    /// the image of the item with the id, shown as a placeholder until then
+    Item(ItemId),
+    /// a web page to pick images from
+    Page,
+    /// an image on a web page
+    Choice,
+}
+
+/// how far along a download is, shared with the worker doing it
+#[derive(Default)]
+struct Progress {
+    cancelled: AtomicBool,
+    /// bytes so far
+    done: AtomicU64,
+    /// 0 if the size isnt known
+    total: AtomicU64,
+}
+
+struct Job {
+    id: u64,
+    source: String,
+    progress: Arc<Progress>,
+}
+
+/// whats been downloaded
+#[derive(Debug, PartialEq)]
+pub struct Download {
+    pub bytes: Vec<u8>,
+    /// what the server said it is
+    pub content_type: Option<String>,
+}
+
+/// a finished download: (purpose, source, the download or why it failed)
+pub type Finished = (Purpose, String, Result<Download, String>);
+
+/// work on a cached image in the cache at `dir`
+enum Decode {
+    /// turns it into pixels for the item with the id
+    Pixels {
+        id: ItemId,
+        dir: PathBuf,
+        blob: String,
+        tier: Option<u32>,
+    },
+    /// makes its missing thumbnails
+    Thumbnails { dir: PathBuf, blob: String },
+}
+
+/// a finished decode: (item id, blob, thumbnail tier, the pixels or why it failed)
+pub type Decoded = (
+    ItemId,
+    String,
+    Option<u32>,
+    Result<image::RgbaImage, String>,
+);
+
+/// downloads things and decodes cached images on background threads so the window doesnt freeze
+pub struct Fetcher {
+    jobs: Sender<Job>,
+    results: Receiver<(u64, Result<Download, String>)>,
+    decodes: Sender<Decode>,
+    decoded: Receiver<Decoded>,
+    /// (purpose, source, progress) of each download by id
+    pending: HashMap<u64, (Purpose, String, Arc<Progress>)>,
+    next_id: u64,
+}
+
+impl Fetcher {
+    const WORKERS: usize = 4;
+    /// kept apart from the download workers so slow downloads dont hold up images on screen
+    const DECODERS: usize = 2;
+    const ATTEMPTS: u32 = 3;
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new() -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Self::TIMEOUT)
+            .connect_timeout(Self::TIMEOUT / 3)
+            .build()
+            .unwrap_or_default();
+
+        for _ in 0..Self::WORKERS {
+            let (jobs, results, client) =
+                (job_receiver.clone(), result_sender.clone(), client.clone());
+            std::thread::spawn(move || loop {
+                // the lock is only held while waiting for the next job
+                let Ok(job) = jobs.lock().unwrap().recv() else {
+                    return;
+                };
+                if job.progress.cancelled.load(Ordering::Relaxed) {
+                    continue;
+                }
+
+                let result = download(&client, &job.source, &job.progress);
+                if results.send((job.id, result)).is_err() {
+                    return;
+                }
+            });
+        }
+
+        let (decodes, decode_receiver) = mpsc::channel::<Decode>();
+        let (decoded_sender, decoded) = mpsc::channel();
+        let decode_receiver = Arc::new(Mutex::new(decode_receiver));
+        for _ in 0..Self::DECODERS {
+            let (decodes, decoded) = (decode_receiver.clone(), decoded_sender.clone());
+            std::thread::spawn(move || loop {
+                let Ok(job) = decodes.lock().unwrap().recv() else {
+                    return;
+                };
+
+                match job {
+                    Decode::Pixels {
+                        id,
+                        dir,
+                        blob,
+                        tier,
+                    } => {
+                        let result = cache::decode(&dir, &blob, tier).map_err(|e| e.to_string());
+                        if decoded.send((id, blob, tier, result)).is_err() {
+                            return;
+                        }
+                    }
+                    Decode::Thumbnails { dir, blob } => {
+                        if let Err(e) = cache::make_thumbnails(&dir, &blob) {
+                            println!("couldnt make thumbnails of {blob}: {e}");
+                        }
+                    }
+                }
+            });
+        }
+
+        Self {
+            jobs,
+            results,
+            decodes,
+            decoded,
+            pending: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// starts downloading `source`, unless its already being downloaded for the same purpose
+    pub fn request(&mut self, source: &str, purpose: Purpose) {
+        if self
+            .pending
+            .values()
+            .any(|(p, s, _)| *p == purpose && s == source)
+        {
+            return;
+        }
+
+        self.next_id += 1;
+        let progress = Arc::new(Progress::default());
+        self.pending
+            .insert(self.next_id, (purpose, source.to_owned(), progress.clone()));
+        _ = self.jobs.send(Job {
+            id: self.next_id,
+            source: source.to_owned(),
+            progress,
+        });
+    }
+
+    /// stops every download whose purpose satisfies `f`, returns how many were stopped
+    pub fn cancel(&mut self, f: impl Fn(&Purpose) -> bool) -> usize {
+        let before = self.pending.len();
+        self.pending.retain(|_, (purpose, _, progress)| {
+            let cancel = f(purpose);
+            if cancel {
+                progress.cancelled.store(true, Ordering::Relaxed);
+            }
+            !cancel
+        });
+
+        before - self.pending.len()
+    }
+
+    /// downloads which finished since the last poll, cancelled ones are left out
+    pub fn poll(&mut self) -> Vec<Finished> {
+        self.results
+            .try_iter()
+            .filter_map(|(id, result)| {
+                let (purpose, source, _) = self.pending.remove(&id)?;
+                Some((purpose, source, result))
+            })
+            .collect()
+    }
+
+    /// starts decoding `blob` from the cache at `dir` as thumbnail `tier` (the full image for [`None`])
+    /// for the item with the id
+    pub fn decode(&self, id: ItemId, dir: &Path, blob: &str, tier: Option<u32>) {
+        _ = self.decodes.send(Decode::Pixels {
+            id,
+            dir: dir.to_owned(),
+            blob: blob.to_owned(),
+            tier,
+        });
+    }
+
+    /// starts making the missing thumbnails of `blob` in the cache at `dir`
+    pub fn make_thumbnails(&self, dir: &Path, blob: &str) {
+        _ = self.decodes.send(Decode::Thumbnails {
+            dir: dir.to_owned(),
+            blob: blob.to_owned(),
+        });
+    }
+
+    /// decodes which finished since the last poll
+    pub fn poll_decoded(&self) -> Vec<Decoded> {
+        self.decoded.try_iter().collect()
+    }
+
+    /// how far along the download for `purpose` is from 0 to 1, [`None`] if theres no such download
+    /// or its size isnt known
+    pub fn progress(&self, purpose: &Purpose) -> Option<f32> {
+        let (_, _, progress) = self.pending.values().find(|(p, _, _)| p == purpose)?;
+        let total = progress.total.load(Ordering::Relaxed);
+        (total != 0).then(|| progress.done.load(Ordering::Relaxed) as f32 / total as f32)
+    }
+
+    /// whether theres a download for `purpose`
+    pub fn is_pending(&self, purpose: &Purpose) -> bool {
+        self.pending.values().any(|(p, _, _)| p == purpose)
+    }
+
+    /// how many downloads havent finished yet
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// downloads `source`, trying again a few times with a growing wait in between
+fn download(
+    client: &reqwest::blocking::Client,
+    source: &str,
+    progress: &Progress,
+) -> Result<Download, String> {
+    let mut error = String::new();
+    for attempt in 0..Fetcher::ATTEMPTS {
+        if attempt != 0 {
+            std::thread::sleep(Duration::from_millis(500 << attempt));
+        }
+        if progress.cancelled.load(Ordering::Relaxed) {
+            return Err("cancelled".to_owned());
+        }
+
+        match try_download(client, source, progress) {
+            Ok(download) => return Ok(download),
+            Err(e) => error = e.to_string(),
+        }
+    }
+
+    Err(error)
+}
+
+fn try_download(
+    client: &reqwest::blocking::Client,
+    source: &str,
+    progress: &Progress,
+) -> Result<Download, Box<dyn std::error::Error>> {
+    let mut response = client.get(source).send()?.error_for_status()?;
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|t| t.to_str().ok())
+        .map(str::to_owned);
+    progress
+        .total
+        .store(response.content_length().unwrap_or(0), Ordering::Relaxed);
+    progress.done.store(0, Ordering::Relaxed);
+
+    let mut bytes = Vec::new();
+    let mut chunk = [0; 16 * 1024];
+    loop {
+        if progress.cancelled.load(Ordering::Relaxed) {
+            return Err("cancelled".into());
+        }
+
+        let read = response.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(Download {
+                bytes,
+                content_type,
+            });
+        }
+        bytes.extend_from_slice(&chunk[..read]);
+        progress.done.fetch_add(read as u64, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::time::{Duration, Instant};
+
+    use super::{Download, Fetcher, Purpose};
+
+    #[test]
+    fn downloads_finish_in_the_background() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 1024];
+                _ = stream.read(&mut request);
+                let body = "image bytes";
+                _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+            }
+        });
+
+        let mut fetcher = Fetcher::new();
+        let source = format!("http://{address}/cat.png");
+        fetcher.request(&source, Purpose::Item(1));
+        fetcher.request(&source, Purpose::Item(1));
+        fetcher.request(&source, Purpose::Item(2));
+        assert_eq!(fetcher.len(), 2);
+
+        // cancelled downloads are never handed back
+        assert_eq!(fetcher.cancel(|p| *p == Purpose::Item(2)), 1);
+
+        let start = Instant::now();
+        let mut finished = Vec::new();
+        while fetcher.len() != 0 && start.elapsed() < Duration::from_secs(10) {
+            finished.extend(fetcher.poll());
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            finished,
+            [(
+                Purpose::Item(1),
+                source,
+                Ok(Download {
+                    bytes: b"image bytes".to_vec(),
+                    content_type: Some("image/png".to_owned()),
+                })
+            )]
+        );
+    }
+}
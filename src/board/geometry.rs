@@ -0,0 +1,185 @@
+/// rect which is rotated around its centre
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrientedRect {
+    /// the rect before rotation: (x, y, w, h)
+    pub rect: (f32, f32, f32, f32),
+    pub rotation: f32,
+}
+
+impl OrientedRect {
+    pub fn new(rect: (f32, f32, f32, f32), rotation: f32) -> Self {
+        Self { rect, rotation }
+    }
+
+    #[inline]
+    pub fn centre(&self) -> (f32, f32) {
+        (
+            self.rect.0 + self.rect.2 / 2.0,
+            self.rect.1 + self.rect.3 / 2.0,
+        )
+    }
+
+    /// rotates `p` (relative to the centre) by the rects rotation
+    #[inline]
+    fn rotate(&self, p: (f32, f32)) -> (f32, f32) {
+        let (sin, cos) = self.rotation.sin_cos();
+        (p.0 * cos - p.1 * sin, p.0 * sin + p.1 * cos)
+    }
+
+    /// where to draw from so that rotating around it (like ggez does) ends up rotating around the centre
+    pub fn origin(&self) -> (f32, f32) {
+        let centre = self.centre();
+        let half = self.rotate((self.rect.2 / 2.0, self.rect.3 / 2.0));
+        (centre.0 - half.0, centre.1 - half.1)
+    }
+
+    /// world position of the point `local` (relative to the top left of the rect before rotation)
+    pub fn world_point(&self, local: (f32, f32)) -> (f32, f32) {
+        let centre = self.centre();
+        let p = self.rotate((local.0 - self.rect.2 / 2.0, local.1 - self.rect.3 / 2.0));
+        (centre.0 + p.0, centre.1 + p.1)
+    }
+
+    /// top left, top right, bottom right, bottom left (before rotation)
+    pub fn corners(&self) -> [(f32, f32); 4] {
+        let centre = self.centre();
+        let (w, h) = (self.rect.2 / 2.0, self.rect.3 / 2.0);
+
+        [(-w, -h), (w, -h), (w, h), (-w, h)].map(|p| {
+            let p = self.rotate(p);
+            (centre.0 + p.0, centre.1 + p.1)
+        })
+    }
+
+    /// `p` relative to the centre with the rotation undone
+    #[inline]
+    fn unrotate(&self, p: (f32, f32)) -> (f32, f32) {
+        let centre = self.centre();
+        let (sin, cos) = self.rotation.sin_cos();
+        let d = (p.0 - centre.0, p.1 - centre.1);
+        (d.0 * cos + d.1 * sin, -d.0 * sin + d.1 * cos)
+    }
+
+    pub fn contains(&self, p: (f32, f32)) -> bool {
+        let local = self.unrotate(p);
+        local.0.abs() <= self.rect.2 / 2.0 && local.1.abs() <= self.rect.3 / 2.0
+    }
+
+    /// where the line from the centre to `p` leaves the rect (the centre if `p` is inside)
+    pub fn edge_towards(&self, p: (f32, f32)) -> (f32, f32) {
+        let local = self.unrotate(p);
+        let scale = (self.rect.2 / 2.0 / local.0.abs()).min(self.rect.3 / 2.0 / local.1.abs());
+        if scale >= 1.0 || scale.is_nan() {
+            return self.centre();
+        }
+
+        let centre = self.centre();
+        let edge = self.rotate((local.0 * scale, local.1 * scale));
+        (centre.0 + edge.0, centre.1 + edge.1)
+    }
+
+    /// axis aligned rect around the rotated rect: (x, y, w, h)
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        let (min, max) = self.corners().iter().fold(
+            ((f32::MAX, f32::MAX), (f32::MIN, f32::MIN)),
+            |(min, max), p| {
+                (
+                    (min.0.min(p.0), min.1.min(p.1)),
+                    (max.0.max(p.0), max.1.max(p.1)),
+                )
+            },
+        );
+
+        (min.0, min.1, max.0 - min.0, max.1 - min.1)
+    }
+
+    /// whether the axis aligned rect `r` touches the rotated rect
+    /// rect `r`: (x, y, w, h)
+    pub fn intersects(&self, r: (f32, f32, f32, f32)) -> bool {
+        let corners = self.corners();
+        let other = [
+            (r.0, r.1),
+            (r.0 + r.2, r.1),
+            (r.0 + r.2, r.1 + r.3),
+            (r.0, r.1 + r.3),
+        ];
+        let (sin, cos) = self.rotation.sin_cos();
+
+        // separating axis theorem, the only axes that matter are the edges of both rects
+        [(1.0, 0.0), (0.0, 1.0), (cos, sin), (-sin, cos)]
+            .iter()
+            .all(|axis| {
+                let project = |ps: &[(f32, f32); 4]| {
+                    ps.iter()
+                        .map(|p| p.0 * axis.0 + p.1 * axis.1)
+                        .fold((f32::MAX, f32::MIN), |(min, max), x| {
+                            (min.min(x), max.max(x))
+                        })
+                };
+                let (a, b) = (project(&corners), project(&other));
+
+                a.0 <= b.1 && b.0 <= a.1
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OrientedRect;
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3
+    }
+
+    #[test]
+    fn rotates_around_centre() {
+        let r = OrientedRect::new((10.0, 20.0, 40.0, 20.0), FRAC_PI_2);
+
+        assert!(close(r.centre(), (30.0, 30.0)));
+        assert!(close(r.corners()[0], (40.0, 10.0)));
+        // the top left corner ends up being where ggez draws from
+        assert!(close(r.origin(), r.corners()[0]));
+        assert!(close(r.world_point((40.0, 20.0)), r.corners()[2]));
+
+        let b = r.bounds();
+        assert!(close((b.0, b.1), (20.0, 10.0)));
+        assert!(close((b.2, b.3), (20.0, 40.0)));
+    }
+
+    #[test]
+    fn contains_takes_rotation_into_account() {
+        let r = OrientedRect::new((0.0, 0.0, 100.0, 10.0), FRAC_PI_4);
+
+        assert!(r.contains(r.centre()));
+        // would be inside if it wasnt rotated
+        assert!(!r.contains((95.0, 5.0)));
+        assert!(!r.contains((5.0, 5.0)));
+        // along the rotated length
+        assert!(r.contains((50.0 + 30.0, 5.0 + 30.0)));
+        assert!(r.contains((50.0 - 30.0, 5.0 - 30.0)));
+    }
+
+    #[test]
+    fn edge_towards_stops_at_the_outline() {
+        let r = OrientedRect::new((0.0, 0.0, 40.0, 20.0), 0.0);
+        assert!(close(r.edge_towards((100.0, 10.0)), (40.0, 10.0)));
+        assert!(close(r.edge_towards((20.0, -100.0)), (20.0, 0.0)));
+        assert!(close(r.edge_towards((25.0, 12.0)), r.centre()));
+
+        let r = OrientedRect::new((0.0, 0.0, 40.0, 20.0), FRAC_PI_2);
+        // the long side is upright now
+        assert!(close(r.edge_towards((20.0, 100.0)), (20.0, 30.0)));
+    }
+
+    #[test]
+    fn intersects_uses_every_axis() {
+        let r = OrientedRect::new((0.0, 0.0, 100.0, 10.0), FRAC_PI_4);
+
+        assert!(r.intersects((45.0, 0.0, 10.0, 10.0)));
+        assert!(r.intersects((-100.0, -100.0, 300.0, 300.0)));
+        // overlaps the bounds but not the rotated rect itself
+        assert!(!r.intersects((85.0, -30.0, 5.0, 5.0)));
+        assert!(!r.intersects((200.0, 0.0, 10.0, 10.0)));
+    }
+}
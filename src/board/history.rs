@@ -0,0 +1,273 @@
+use super::board::{Item, Transform};
+use super::connector::Routing;
+use super::layer::LayerId;
+use super::style::{ShapeStyle, TextStyle};
+
+/// a reversible change to the items on a board
+pub enum Edit {
+    /// inserts the item at the index
+    Insert(usize, Item),
+    /// removes the item at the index
+    Remove(usize),
+    /// sets the transform of the item at the index
+    Transform(usize, Transform),
+    /// puts the items in a new order, the item at `order[i]` ending up at `i`
+    Reorder(Vec<usize>),
+    /// moves the item at the index onto the layer
+    Layer(usize, LayerId),
+    /// sets the contents of the text item (or label of the connector) at the index
+    Text(usize, String),
+    /// sets the style of the text item at the index
+    Style(usize, TextStyle),
+    /// sets the style of the shape, pen stroke or connector at the index
+    ShapeStyle(usize, ShapeStyle),
+    /// sets how the connector at the index is routed
+    Routing(usize, Routing),
+    /// several edits applied in order as one step
+    Group(Vec<Edit>),
+}
+
+impl Edit {
+    /// applies the edit to `items`, giving back the edit which reverses it
+    pub fn apply(self, items: &mut Vec<Item>) -> Edit {
+        match self {
+            Edit::Insert(i, item) => {
+                items.insert(i, item);
+                Edit::Remove(i)
+            }
+            Edit::Remove(i) => Edit::Insert(i, items.remove(i)),
+            Edit::Transform(i, transform) => {
+                let old = items[i].transform();
+                items[i].set_transform(transform);
+                Edit::Transform(i, old)
+            }
+            Edit::Reorder(order) => {
+                let mut old = items.drain(..).map(Some).collect::<Vec<Option<Item>>>();
+                items.extend(order.iter().map(|&i| old[i].take().unwrap()));
+
+                let mut inverse = vec![0; order.len()];
+                for (to, &from) in order.iter().enumerate() {
+                    inverse[from] = to;
+                }
+                Edit::Reorder(inverse)
+            }
+            Edit::Layer(i, layer) => {
+                let old = items[i].layer();
+                items[i].set_layer(layer);
+                Edit::Layer(i, old)
+            }
+            Edit::Text(i, text) => Edit::Text(i, items[i].set_contents(text)),
+            Edit::Style(i, style) => match &mut items[i] {
+                Item::Text(item) => Edit::Style(i, std::mem::replace(&mut item.style, style)),
+                _ => Edit::Style(i, style),
+            },
+            Edit::ShapeStyle(i, style) => match &mut items[i] {
+                Item::Shape(item) => Edit::ShapeStyle(i, std::mem::replace(&mut item.style, style)),
+                Item::Pen(item) => Edit::ShapeStyle(i, std::mem::replace(&mut item.style, style)),
+                Item::Connector(item) => {
+                    Edit::ShapeStyle(i, std::mem::replace(&mut item.style, style))
+                }
+                _ => Edit::ShapeStyle(i, style),
+            },
+            Edit::Routing(i, routing) => match &mut items[i] {
+                Item::Connector(item) => {
+                    Edit::Routing(i, std::mem::replace(&mut item.routing, routing))
+                }
+                _ => Edit::Routing(i, routing),
+            },
+            Edit::Group(edits) => {
+                let mut inverse = edits
+                    .into_iter()
+                    .map(|e| e.apply(items))
+                    .collect::<Vec<Edit>>();
+                inverse.reverse();
+                Edit::Group(inverse)
+            }
+        }
+    }
+
+    /// the index of the one item this edit changes, for edits which change just one
+    fn target(&mut self) -> Option<&mut usize> {
+        match self {
+            Edit::Transform(i, _)
+            | Edit::Layer(i, _)
+            | Edit::Text(i, _)
+            | Edit::Style(i, _)
+            | Edit::ShapeStyle(i, _)
+            | Edit::Routing(i, _) => Some(i),
+            Edit::Insert(..) | Edit::Remove(_) | Edit::Reorder(_) | Edit::Group(_) => None,
+        }
+    }
+
+    /// this edit as if the item at `i` (before its applied) had never been there,
+    /// along with where that item is after its applied ([`None`] if the edit removes it)
+    fn without(mut self, i: usize) -> (Option<Edit>, Option<usize>) {
+        match self {
+            Edit::Insert(j, item) if j <= i => (Some(Edit::Insert(j, item)), Some(i + 1)),
+            Edit::Insert(j, item) => (Some(Edit::Insert(j - 1, item)), Some(i)),
+            Edit::Remove(j) if j == i => (None, None),
+            Edit::Remove(j) if j < i => (Some(Edit::Remove(j)), Some(i - 1)),
+            Edit::Remove(j) => (Some(Edit::Remove(j - 1)), Some(i)),
+            Edit::Reorder(mut order) => {
+                let to = order.iter().position(|&from| from == i);
+                order.retain(|&from| from != i);
+                for from in &mut order {
+                    if *from > i {
+                        *from -= 1;
+                    }
+                }
+                (Some(Edit::Reorder(order)), to)
+            }
+            Edit::Group(edits) => {
+                let mut at = Some(i);
+                let mut kept = Vec::with_capacity(edits.len());
+                for edit in edits {
+                    let Some(i) = at else {
+                        kept.push(edit);
+                        continue;
+                    };
+                    let (edit, i) = edit.without(i);
+                    kept.extend(edit);
+                    at = i;
+                }
+                ((!kept.is_empty()).then_some(Edit::Group(kept)), at)
+            }
+            _ => {
+                let j = self.target().expect("every other edit changes one item");
+                if *j == i {
+                    return (None, Some(i));
+                }
+                if *j > i {
+                    *j -= 1;
+                }
+                (Some(self), Some(i))
+            }
+        }
+    }
+
+    fn into_items(self) -> Vec<Item> {
+        match self {
+            Edit::Insert(_, item) => vec![item],
+            Edit::Group(edits) => edits.into_iter().flat_map(Edit::into_items).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// undo and redo stacks of [`Edit`]s
+/// the stacks hold the edits which reverse what was done (or undone)
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+impl History {
+    /// max amount of steps which can be undone
+    const LIMIT: usize = 128;
+
+    /// records a change which has already been made, `inverse` being the edit which reverses it
+    /// returns the items which can no longer be brought back
+    pub fn record(&mut self, inverse: Edit) -> Vec<Item> {
+        self.undo.push(inverse);
+
+        let mut lost = self
+            .redo
+            .drain(..)
+            .flat_map(Edit::into_items)
+            .collect::<Vec<Item>>();
+        if self.undo.len() > Self::LIMIT {
+            lost.extend(self.undo.remove(0).into_items());
+        }
+
+        lost
+    }
+
+    /// returns the edit which reverses what was undone, [`None`] if there was nothing to undo
+    pub fn undo(&mut self, items: &mut Vec<Item>) -> Option<&Edit> {
+        let edit = self.undo.pop()?;
+        self.redo.push(edit.apply(items));
+        self.redo.last()
+    }
+
+    /// returns the edit which reverses what was redone, [`None`] if there was nothing to redo
+    pub fn redo(&mut self, items: &mut Vec<Item>) -> Option<&Edit> {
+        let edit = self.redo.pop()?;
+        self.undo.push(edit.apply(items));
+        self.undo.last()
+    }
+
+    /// the item at `i` was removed without recording a step (like a placeholder which wasnt an image),
+    /// so every step is changed to act as if it had never been there
+    pub fn removed(&mut self, i: usize) {
+        for stack in [&mut self.undo, &mut self.redo] {
+            // the top of each stack applies to the items as they are now
+            let mut at = Some(i);
+            let mut kept = Vec::with_capacity(stack.len());
+            for edit in stack.drain(..).rev() {
+                let Some(i) = at else {
+                    kept.push(edit);
+                    continue;
+                };
+                let (edit, i) = edit.without(i);
+                kept.extend(edit);
+                at = i;
+            }
+            kept.reverse();
+            *stack = kept;
+        }
+    }
+
+    /// forgets everything, returning the items which can no longer be brought back
+    pub fn clear(&mut self) -> Vec<Item> {
+        self.undo
+            .drain(..)
+            .chain(self.redo.drain(..))
+            .flat_map(Edit::into_items)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Edit, History};
+    use crate::board::board::{Item, ItemText};
+
+    fn contents(items: &[Item]) -> Vec<&str> {
+        items.iter().filter_map(Item::contents).collect()
+    }
+
+    #[test]
+    fn steps_leave_out_removed_items() {
+        let text = |t: &str| Item::Text(ItemText::new(t.to_owned()));
+        let mut items = vec![text("a"), text("b")];
+        let mut history = History::default();
+
+        // adding a placeholder, then editing an item after it and the placeholder itself
+        history.record(Edit::Insert(1, text("placeholder")).apply(&mut items));
+        history.record(
+            Edit::Group(vec![
+                Edit::Text(2, "B".to_owned()),
+                Edit::Text(1, "loading".to_owned()),
+            ])
+            .apply(&mut items),
+        );
+        history.record(Edit::Reorder(vec![2, 0, 1]).apply(&mut items));
+        assert_eq!(contents(&items), ["B", "a", "loading"]);
+
+        items.remove(2);
+        history.removed(2);
+
+        assert!(history.undo(&mut items).is_some());
+        assert_eq!(contents(&items), ["a", "B"]);
+        assert!(history.undo(&mut items).is_some());
+        assert_eq!(contents(&items), ["a", "b"]);
+        // adding the placeholder isnt a step anymore
+        assert!(history.undo(&mut items).is_none());
+
+        assert!(history.redo(&mut items).is_some());
+        assert!(history.redo(&mut items).is_some());
+        assert_eq!(contents(&items), ["B", "a"]);
+        assert!(history.redo(&mut items).is_none());
+    }
+}
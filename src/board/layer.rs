@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+
+/// identifies a layer, 0 being the base layer every board has
+pub type LayerId = u64;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Layer {
+    pub id: LayerId,
+    pub name: String,
+    /// hidden layers arent drawn and their items cant be selected
+    pub hidden: bool,
+    /// locked layers are drawn but their items cant be selected
+    pub locked: bool,
+}
+
+impl Layer {
+    fn new(id: LayerId, name: String) -> Self {
+        Self {
+            id,
+            name,
+            hidden: false,
+            locked: false,
+        }
+    }
+}
+
+impl std::fmt::Display for Layer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if self.hidden {
+            write!(f, " (hidden)")?;
+        }
+        if self.locked {
+            write!(f, " (locked)")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// the layers of a board, items in higher layers are always drawn over items in lower ones
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Layers {
+    /// bottom to top
+    list: Vec<Layer>,
+    /// index of the layer new items go on
+    current: usize,
+}
+
+impl Default for Layers {
+    fn default() -> Self {
+        Self {
+            list: vec![Layer::new(0, "base".to_owned())],
+            current: 0,
+        }
+    }
+}
+
+impl Layers {
+    pub fn read<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let layers = serde_json::from_str::<Layers>(&std::fs::read_to_string(path)?)?;
+        if layers.list.is_empty() || layers.current >= layers.list.len() {
+            return Err("no layers or the current layer doesnt exist".into());
+        }
+
+        Ok(layers)
+    }
+
+    /// how far up `id` is, layers which dont exist (anymore) count as the bottom one
+    #[inline]
+    pub fn rank(&self, id: LayerId) -> usize {
+        self.list.iter().position(|l| l.id == id).unwrap_or(0)
+    }
+
+    #[inline]
+    fn get(&self, id: LayerId) -> &Layer {
+        &self.list[self.rank(id)]
+    }
+
+    #[inline]
+    pub fn visible(&self, id: LayerId) -> bool {
+        !self.get(id).hidden
+    }
+
+    /// whether items on the layer can be selected
+    #[inline]
+    pub fn selectable(&self, id: LayerId) -> bool {
+        let layer = self.get(id);
+        !layer.hidden && !layer.locked
+    }
+
+    #[inline]
+    pub fn current(&self) -> &Layer {
+        &self.list[self.current]
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    /// adds a new layer above the current one and makes it the current one
+    pub fn add(&mut self) -> &Layer {
+        let id = self.list.iter().map(|l| l.id).max().unwrap_or(0) + 1;
+        self.current += 1;
+        self.list
+            .insert(self.current, Layer::new(id, format!("layer {id}")));
+
+        self.current()
+    }
+
+    /// makes the layer `by` layers up (or down) the current one
+    pub fn select(&mut self, by: isize) -> &Layer {
+        self.current = self
+            .current
+            .saturating_add_signed(by)
+            .min(self.list.len() - 1);
+        self.current()
+    }
+
+    /// moves the current layer `by` layers up (or down)
+    pub fn move_current(&mut self, by: isize) -> &Layer {
+        let to = self
+            .current
+            .saturating_add_signed(by)
+            .min(self.list.len() - 1);
+        let layer = self.list.remove(self.current);
+        self.list.insert(to, layer);
+        self.current = to;
+
+        self.current()
+    }
+
+    pub fn toggle_hidden(&mut self) -> &Layer {
+        let layer = &mut self.list[self.current];
+        layer.hidden = !layer.hidden;
+        layer
+    }
+
+    pub fn toggle_locked(&mut self) -> &Layer {
+        let layer = &mut self.list[self.current];
+        layer.locked = !layer.locked;
+        layer
+    }
+
+    pub fn rename(&mut self, name: String) -> &Layer {
+        let layer = &mut self.list[self.current];
+        layer.name = name;
+        layer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Layers;
+
+    #[test]
+    fn adding_and_moving_layers() {
+        let mut layers = Layers::default();
+        let a = layers.add().id;
+        let b = layers.add().id;
+        assert_eq!((layers.rank(0), layers.rank(a), layers.rank(b)), (0, 1, 2));
+
+        // new layers go right above the current one
+        layers.select(-1);
+        let c = layers.add().id;
+        assert_eq!((layers.rank(a), layers.rank(c), layers.rank(b)), (1, 2, 3));
+
+        layers.move_current(-10);
+        assert_eq!(layers.rank(c), 0);
+        assert_eq!(layers.current().id, c);
+        layers.select(10);
+        assert_eq!(layers.current().id, b);
+
+        assert!(layers.selectable(b));
+        layers.toggle_locked();
+        assert!(layers.visible(b) && !layers.selectable(b));
+        // layers which dont exist are treated like the bottom one
+        assert_eq!(layers.rank(100), 0);
+    }
+}
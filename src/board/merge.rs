@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use super::board::{Item, ItemId};
+
+/// how an item is compared between versions of the board
+#[inline]
+pub fn serialise(item: &Item) -> String {
+    serde_json::to_string(item).unwrap_or_default()
+}
+
+/// the items as theyd be saved, by id
+pub fn snapshot(items: &[Item]) -> HashMap<ItemId, String> {
+    items.iter().map(|i| (i.id(), serialise(i))).collect()
+}
+
+/// merges the board as it was changed elsewhere (`external`) into the `local` one,
+/// `base` being how both were when last saved or loaded
+/// changes on either side are kept, if both changed the same item the local change wins
+/// new items which ended up with the same id on both sides get a new id from `next_id`
+pub fn merge(
+    base: &HashMap<ItemId, String>,
+    local: Vec<Item>,
+    external: Vec<Item>,
+    next_id: &mut ItemId,
+) -> Vec<Item> {
+    let changed_locally = |i: &Item| base.get(&i.id()) != Some(&serialise(i));
+
+    let mut external = external
+        .into_iter()
+        .map(|i| (i.id(), i))
+        .collect::<HashMap<ItemId, Item>>();
+    // so new ids cant clash with anything on either side
+    *next_id = external
+        .keys()
+        .copied()
+        .chain(local.iter().map(Item::id))
+        .fold(*next_id, ItemId::max);
+
+    let mut merged = Vec::with_capacity(local.len());
+    for item in local {
+        let id = item.id();
+        match external.remove(&id) {
+            // new on both sides, the external one gets added after
+            Some(theirs) if !base.contains_key(&id) => {
+                merged.push(item);
+                *next_id += 1;
+                external.insert(*next_id, theirs.with_id(*next_id));
+            }
+            Some(theirs) if !changed_locally(&item) => merged.push(theirs),
+            Some(_) => merged.push(item),
+            // removed elsewhere, unless its new or changed here
+            None if base.contains_key(&id) && !changed_locally(&item) => (),
+            None => merged.push(item),
+        }
+    }
+
+    // added elsewhere, unless they were removed here
+    let mut added = external
+        .into_values()
+        .filter(|i| !base.contains_key(&i.id()))
+        .collect::<Vec<Item>>();
+    added.sort_by_key(Item::id);
+    merged.extend(added);
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge, snapshot};
+    use crate::board::board::{Item, ItemText};
+
+    fn text(id: u64, s: &str) -> Item {
+        Item::Text(ItemText::new(s.to_owned())).with_id(id)
+    }
+
+    fn texts(items: &[Item]) -> Vec<(u64, &str)> {
+        items
+            .iter()
+            .map(|i| (i.id(), i.contents().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn changes_from_both_sides_are_kept() {
+        let base = snapshot(&[text(1, "a"), text(2, "b"), text(3, "c"), text(4, "d")]);
+        // changed 1, removed 2, added 5
+        let local = vec![text(1, "a local"), text(3, "c"), text(4, "d"), text(5, "e")];
+        // changed 1 and 3, removed 4, added 5 too
+        let external = vec![
+            text(1, "a external"),
+            text(2, "b"),
+            text(3, "c external"),
+            text(5, "f"),
+        ];
+
+        let mut next_id = 5;
+        let merged = merge(&base, local, external, &mut next_id);
+        assert_eq!(
+            texts(&merged),
+            [(1, "a local"), (3, "c external"), (5, "e"), (6, "f")]
+        );
+        assert_eq!(next_id, 6);
+    }
+}
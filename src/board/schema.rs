@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::board::Item;
+use super::style::TextStyle;
+
+/// version of the store format this build writes
+/// stores from before versions existed (without a header) are version 0
+pub const VERSION: u32 = 1;
+
+/// first line of the store
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Header {
+    pub version: u32,
+}
+
+impl Header {
+    pub fn current() -> Self {
+        Self { version: VERSION }
+    }
+}
+
+/// upgrades an item from version `i` to `i + 1`
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+const MIGRATIONS: [Migration; VERSION as usize] = [v0_to_v1];
+
+/// items didnt always have a layer and text items didnt have a style (being plain)
+fn v0_to_v1(item: &mut Value) -> Result<(), String> {
+    let (kind, fields) = item
+        .as_object_mut()
+        .and_then(|o| o.iter_mut().next())
+        .ok_or("item isnt an object")?;
+    let fields = fields
+        .as_object_mut()
+        .ok_or("item fields arent an object")?;
+
+    fields.entry("layer").or_insert(0.into());
+    if kind == "Text" && !fields.contains_key("style") {
+        let plain = serde_json::to_value(TextStyle::plain()).map_err(|e| e.to_string())?;
+        fields.insert("style".to_owned(), plain);
+    }
+
+    Ok(())
+}
+
+/// the version of the store and the lines with its items
+/// stores newer than this build cant be read
+pub fn split_header(contents: &str) -> Result<(u32, Vec<&str>), String> {
+    let mut lines = contents
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .collect::<Vec<&str>>();
+
+    let version = match lines.first().map(|l| serde_json::from_str::<Header>(l)) {
+        Some(Ok(header)) => {
+            lines.remove(0);
+            header.version
+        }
+        _ => 0,
+    };
+    if version > VERSION {
+        return Err(format!(
+            "the store is version {version} but only up to {VERSION} can be read"
+        ));
+    }
+
+    Ok((version, lines))
+}
+
+/// reads an item saved by `version`, upgrading it to the current one
+pub fn migrate(line: &str, version: u32) -> Result<Item, String> {
+    let mut item = serde_json::from_str::<Value>(line).map_err(|e| e.to_string())?;
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(&mut item)?;
+    }
+
+    serde_json::from_value(item).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{migrate, split_header, Header, VERSION};
+    use crate::board::board::Item;
+    use crate::board::style::TextStyle;
+
+    #[test]
+    fn old_stores_are_upgraded() {
+        let old = r#"{"Text":{"text":"hi","position":[0.0,0.0],"scale":1.0,"rotation":0.0}}"#;
+        let (version, lines) = split_header(old).unwrap();
+        assert_eq!((version, lines.len()), (0, 1));
+
+        let Item::Text(text) = migrate(lines[0], version).unwrap() else {
+            panic!("not a text item");
+        };
+        assert_eq!((&text.style, text.layer), (&TextStyle::plain(), 0));
+
+        // already current items arent touched
+        let current = serde_json::to_string(&Item::Text(text)).unwrap();
+        assert_eq!(
+            serde_json::to_string(&migrate(&current, VERSION).unwrap()).unwrap(),
+            current
+        );
+    }
+
+    #[test]
+    fn headers() {
+        let header = serde_json::to_string(&Header::current()).unwrap();
+        assert_eq!(
+            split_header(&format!("{header}\na\n\nb\n")),
+            Ok((VERSION, vec!["a", "b"]))
+        );
+        assert_eq!(split_header(""), Ok((0, vec![])));
+        assert!(split_header(r#"{"version":99999}"#).is_err());
+    }
+}
@@ -0,0 +1,386 @@
+use ggez::graphics::{self, Canvas, Color, DrawMode, DrawParam, MeshBuilder};
+use ggez::Context;
+use serde::{Deserialize, Serialize};
+
+use super::board::ItemId;
+use super::geometry::OrientedRect;
+use super::layer::LayerId;
+use super::style::ShapeStyle;
+use crate::camera::Camera;
+
+/// what the mouse adds to the board when pressed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Tool {
+    /// selects and manages items
+    #[default]
+    Select,
+    Rectangle,
+    Ellipse,
+    Line,
+    Arrow,
+    Pen,
+}
+
+impl std::fmt::Display for Tool {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Tool::Select => "select",
+                Tool::Rectangle => "rectangle",
+                Tool::Ellipse => "ellipse",
+                Tool::Line => "line",
+                Tool::Arrow => "arrow",
+                Tool::Pen => "pen",
+            }
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ShapeKind {
+    Rectangle,
+    Ellipse,
+    /// runs along the middle of the shapes rect, from its left edge to its right edge
+    Line {
+        /// whether there are arrowheads at the (start, end)
+        arrows: (bool, bool),
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ItemShape {
+    /// stays the same for as long as the item exists, unlike its index
+    #[serde(default)]
+    pub id: ItemId,
+    /// the layer the item is on
+    #[serde(default)]
+    pub layer: LayerId,
+    pub kind: ShapeKind,
+    pub style: ShapeStyle,
+
+    pub position: (f32, f32),
+    /// in world units
+    pub size: (f32, f32),
+    pub rotation: f32,
+}
+
+/// freehand stroke
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ItemPen {
+    /// stays the same for as long as the item exists, unlike its index
+    #[serde(default)]
+    pub id: ItemId,
+    /// the layer the item is on
+    #[serde(default)]
+    pub layer: LayerId,
+    /// relative to `position` (before scaling), none are negative
+    points: Vec<(f32, f32)>,
+    pub style: ShapeStyle,
+
+    pub position: (f32, f32),
+    pub scale: (f32, f32),
+    pub rotation: f32,
+}
+
+/// the screen position of the point `local` in the rect
+#[inline]
+fn screen(cam: Camera, rect: OrientedRect, local: (f32, f32)) -> [f32; 2] {
+    let p = cam.world_to_screen(rect.world_point(local));
+    [p.0, p.1]
+}
+
+/// draws whatever was built, ignoring shapes too small to make a mesh out of
+pub fn draw_mesh(c: &mut Canvas, cc: &Context, mesh: &MeshBuilder) {
+    let mesh = mesh.build();
+    if !mesh.vertices.is_empty() {
+        c.draw(&graphics::Mesh::from_data(cc, mesh), DrawParam::new());
+    }
+}
+
+/// adds an arrowhead pointing at `tip` coming from the direction of `from`, all in screen coords
+/// the head grows with the lines `width` but not past half of the line
+pub fn arrowhead(mesh: &mut MeshBuilder, tip: [f32; 2], from: [f32; 2], width: f32, colour: Color) {
+    let d = (tip[0] - from[0], tip[1] - from[1]);
+    let line = d.0.hypot(d.1);
+    if line == 0.0 {
+        return;
+    }
+
+    let dir = (d.0 / line, d.1 / line);
+    let length = (width * 4.0 + 12.0).min(line / 2.0);
+    let half = length * 0.6;
+    let base = (tip[0] - dir.0 * length, tip[1] - dir.1 * length);
+
+    _ = mesh.polygon(
+        DrawMode::fill(),
+        &[
+            tip,
+            [base.0 - dir.1 * half, base.1 + dir.0 * half],
+            [base.0 + dir.1 * half, base.1 - dir.0 * half],
+        ],
+        colour,
+    );
+}
+
+impl ItemShape {
+    /// height of a lines rect, so that thin lines can still be clicked
+    const LINE_HEIGHT: f32 = 24.0;
+
+    pub fn new(kind: ShapeKind, style: ShapeStyle) -> Self {
+        Self {
+            id: 0,
+            layer: 0,
+            kind,
+            style,
+            position: (0.0, 0.0),
+            size: (0.0, 0.0),
+            rotation: 0.0,
+        }
+    }
+
+    pub fn for_tool(tool: Tool, style: ShapeStyle) -> Option<Self> {
+        Some(Self::new(
+            match tool {
+                Tool::Rectangle => ShapeKind::Rectangle,
+                Tool::Ellipse => ShapeKind::Ellipse,
+                Tool::Line => ShapeKind::Line {
+                    arrows: (false, false),
+                },
+                Tool::Arrow => ShapeKind::Line {
+                    arrows: (false, true),
+                },
+                Tool::Select | Tool::Pen => return None,
+            },
+            style,
+        ))
+    }
+
+    /// stretches the shape between the world positions `from` and `to`
+    /// lines go from one to the other, everything else fills the box between them
+    pub fn span(&mut self, from: (f32, f32), to: (f32, f32)) {
+        match self.kind {
+            ShapeKind::Line { .. } => {
+                let d = (to.0 - from.0, to.1 - from.1);
+                let length = d.0.hypot(d.1);
+                let height = Self::LINE_HEIGHT.max(self.style.width * 2.0);
+                let centre = ((from.0 + to.0) / 2.0, (from.1 + to.1) / 2.0);
+
+                self.position = (centre.0 - length / 2.0, centre.1 - height / 2.0);
+                self.size = (length, height);
+                self.rotation = d.1.atan2(d.0);
+            }
+            _ => {
+                self.position = (from.0.min(to.0), from.1.min(to.1));
+                self.size = ((to.0 - from.0).abs(), (to.1 - from.1).abs());
+                self.rotation = 0.0;
+            }
+        }
+    }
+
+    /// the world positions a line runs between (start, end), [`None`] for other shapes
+    pub fn ends(&self) -> Option<((f32, f32), (f32, f32))> {
+        let ShapeKind::Line { .. } = self.kind else {
+            return None;
+        };
+        let r = self.oriented();
+        Some((
+            r.world_point((0.0, self.size.1 / 2.0)),
+            r.world_point((self.size.0, self.size.1 / 2.0)),
+        ))
+    }
+
+    /// whether its too small to be worth keeping
+    pub fn is_empty(&self) -> bool {
+        match self.kind {
+            ShapeKind::Line { .. } => self.size.0 < 1.0,
+            _ => self.size.0 < 1.0 || self.size.1 < 1.0,
+        }
+    }
+
+    #[inline]
+    pub fn oriented(&self) -> OrientedRect {
+        OrientedRect::new(
+            (self.position.0, self.position.1, self.size.0, self.size.1),
+            self.rotation,
+        )
+    }
+
+    /// `text` being the boards text colour, which unstyled strokes use
+    pub fn draw(&self, cam: Camera, c: &mut Canvas, cc: &Context, text: Color) {
+        let rect = self.oriented();
+        let (w, h) = self.size;
+        let stroke = DrawMode::stroke(self.style.width * cam.zoom);
+        let colour = self.style.stroke_colour(text);
+        let mut mesh = MeshBuilder::new();
+
+        // broken meshes (like ones with no area) just arent drawn
+        let outline: Vec<[f32; 2]> = match self.kind {
+            ShapeKind::Rectangle => [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)]
+                .map(|p| screen(cam, rect, p))
+                .to_vec(),
+            ShapeKind::Ellipse => (0..64)
+                .map(|i| {
+                    let (sin, cos) = (i as f32 / 64.0 * std::f32::consts::TAU).sin_cos();
+                    screen(cam, rect, (w / 2.0 * (1.0 + cos), h / 2.0 * (1.0 + sin)))
+                })
+                .collect(),
+            ShapeKind::Line { arrows } => {
+                let start = screen(cam, rect, (0.0, h / 2.0));
+                let end = screen(cam, rect, (w, h / 2.0));
+                let width = self.style.width * cam.zoom;
+                _ = mesh.line(&[start, end], width, colour);
+
+                if arrows.0 {
+                    arrowhead(&mut mesh, start, end, width, colour);
+                }
+                if arrows.1 {
+                    arrowhead(&mut mesh, end, start, width, colour);
+                }
+
+                draw_mesh(c, cc, &mesh);
+                return;
+            }
+        };
+
+        if let Some(fill) = self.style.fill_colour() {
+            _ = mesh.polygon(DrawMode::fill(), &outline, fill);
+        }
+        _ = mesh.polygon(stroke, &outline, colour);
+        draw_mesh(c, cc, &mesh);
+    }
+}
+
+impl ItemPen {
+    pub fn new(style: ShapeStyle, start: (f32, f32)) -> Self {
+        Self {
+            id: 0,
+            layer: 0,
+            points: vec![(0.0, 0.0)],
+            style,
+            position: start,
+            scale: (1.0, 1.0),
+            rotation: 0.0,
+        }
+    }
+
+    /// adds the world position `p` to the end of the stroke while its being drawn (so isnt scaled or rotated)
+    pub fn push_point(&mut self, p: (f32, f32)) {
+        let mut local = (p.0 - self.position.0, p.1 - self.position.1);
+
+        // points stay relative to the top left of the stroke
+        let shift = (local.0.min(0.0), local.1.min(0.0));
+        if shift != (0.0, 0.0) {
+            for q in &mut self.points {
+                *q = (q.0 - shift.0, q.1 - shift.1);
+            }
+            self.position = (self.position.0 + shift.0, self.position.1 + shift.1);
+            local = (local.0 - shift.0, local.1 - shift.1);
+        }
+
+        self.points.push(local);
+    }
+
+    /// world position of the last point
+    pub fn last_point(&self) -> (f32, f32) {
+        let p = self.points.last().copied().unwrap_or_default();
+        (
+            self.position.0 + p.0 * self.scale.0,
+            self.position.1 + p.1 * self.scale.1,
+        )
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.points.len() < 2
+    }
+
+    /// the strokes rect is padded by half its width so it covers the whole stroke
+    pub fn oriented(&self) -> OrientedRect {
+        let extent = self
+            .points
+            .iter()
+            .fold((0.0f32, 0.0f32), |m, p| (m.0.max(p.0), m.1.max(p.1)));
+        let pad = self.style.width / 2.0;
+
+        OrientedRect::new(
+            (
+                self.position.0 - pad,
+                self.position.1 - pad,
+                extent.0 * self.scale.0 + pad * 2.0,
+                extent.1 * self.scale.1 + pad * 2.0,
+            ),
+            self.rotation,
+        )
+    }
+
+    /// `text` being the boards text colour, which unstyled strokes use
+    pub fn draw(&self, cam: Camera, c: &mut Canvas, cc: &Context, text: Color) {
+        let rect = self.oriented();
+        let pad = self.style.width / 2.0;
+        let points = self
+            .points
+            .iter()
+            .map(|p| {
+                screen(
+                    cam,
+                    rect,
+                    (p.0 * self.scale.0 + pad, p.1 * self.scale.1 + pad),
+                )
+            })
+            .collect::<Vec<[f32; 2]>>();
+
+        let mut mesh = MeshBuilder::new();
+        _ = mesh.line(
+            &points,
+            self.style.width * cam.zoom,
+            self.style.stroke_colour(text),
+        );
+        draw_mesh(c, cc, &mesh);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ItemPen, ItemShape, Tool};
+    use crate::board::style::ShapeStyle;
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3
+    }
+
+    #[test]
+    fn lines_run_between_their_ends() {
+        let mut line = ItemShape::for_tool(Tool::Arrow, ShapeStyle::default()).unwrap();
+        line.span((10.0, 10.0), (-20.0, 50.0));
+        let (start, end) = line.ends().unwrap();
+        assert!(close(start, (10.0, 10.0)));
+        assert!(close(end, (-20.0, 50.0)));
+
+        let mut rect = ItemShape::for_tool(Tool::Rectangle, ShapeStyle::default()).unwrap();
+        rect.span((10.0, 10.0), (-20.0, 50.0));
+        assert_eq!(rect.oriented().rect, (-20.0, 10.0, 30.0, 40.0));
+        assert_eq!(rect.ends(), None);
+        rect.span((10.0, 10.0), (10.0, 50.0));
+        assert!(rect.is_empty());
+    }
+
+    #[test]
+    fn pen_points_stay_relative_to_the_top_left() {
+        let mut pen = ItemPen::new(ShapeStyle::default(), (100.0, 100.0));
+        assert!(pen.is_empty());
+
+        pen.push_point((110.0, 90.0));
+        pen.push_point((80.0, 120.0));
+        assert_eq!(pen.position, (80.0, 90.0));
+        assert_eq!(pen.points, [(20.0, 10.0), (30.0, 0.0), (0.0, 30.0)]);
+        assert_eq!(pen.last_point(), (80.0, 120.0));
+
+        let pad = pen.style.width / 2.0;
+        assert_eq!(
+            pen.oriented().rect,
+            (80.0 - pad, 90.0 - pad, 30.0 + pad * 2.0, 30.0 + pad * 2.0)
+        );
+    }
+}
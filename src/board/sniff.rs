@@ -0,0 +1,164 @@
+use std::path::PathBuf;
+
+use reqwest::Url;
+
+/// extensions of files which are (almost certainly) images
+const IMAGE_EXTENSIONS: [&str; 11] = [
+    "png", "jpg", "jpeg", "gif", "webp", "bmp", "ico", "tif", "tiff", "svg", "avif",
+];
+
+/// what a download turned out to be
+#[derive(Debug, PartialEq)]
+pub enum Content {
+    Image,
+    Html,
+    Unknown,
+}
+
+/// works out what `bytes` are from their first few bytes, falling back on the `content_type` the server sent
+/// the bytes win since servers often send the wrong type (or none at all)
+pub fn sniff(bytes: &[u8], content_type: Option<&str>) -> Content {
+    const MAGIC: [&[u8]; 8] = [
+        b"\x89PNG\r\n\x1a\n",
+        b"\xff\xd8\xff",
+        b"GIF87a",
+        b"GIF89a",
+        b"BM",
+        b"\x00\x00\x01\x00",
+        b"II*\x00",
+        b"MM\x00*",
+    ];
+
+    if MAGIC.iter().any(|m| bytes.starts_with(m))
+        || (bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP"))
+        || bytes.get(4..12).is_some_and(|b| b == b"ftypavif")
+    {
+        return Content::Image;
+    }
+
+    if is_svg(bytes) {
+        return Content::Image;
+    }
+    let start = text_start(bytes);
+    if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        return Content::Html;
+    }
+
+    let mime = content_type
+        .and_then(|t| t.split(';').next())
+        .map(|t| t.trim().to_lowercase());
+    match mime.as_deref() {
+        Some(m) if m.starts_with("image/") => Content::Image,
+        Some("text/html" | "application/xhtml+xml") => Content::Html,
+        // html without a doctype, like some error pages
+        _ if start.starts_with('<') => Content::Html,
+        _ => Content::Unknown,
+    }
+}
+
+/// whether `bytes` are an svg image
+pub fn is_svg(bytes: &[u8]) -> bool {
+    let start = text_start(bytes);
+    start.starts_with("<svg") || (start.starts_with("<?xml") && start.contains("<svg"))
+}
+
+/// the start of `bytes` as lowercase text
+/// text formats can start with a byte order mark, whitespace or comments
+fn text_start(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(&bytes[..bytes.len().min(512)])
+        .trim_start_matches('\u{feff}')
+        .trim_start()
+        .to_lowercase()
+}
+
+/// whether the path of `url` (leaving out any query) ends like an image file
+pub fn looks_like_image(url: &str) -> bool {
+    Url::parse(url)
+        .ok()
+        .and_then(|u| {
+            let extension = u.path().rsplit_once('.')?.1.to_lowercase();
+            Some(IMAGE_EXTENSIONS.contains(&extension.as_str()))
+        })
+        .unwrap_or(false)
+}
+
+/// the absolute path `argument` points at if its a local one: an absolute path, `file://` uri,
+/// path in the home directory (`~/`) or a path relative to the working directory which exists
+pub fn local_path(argument: &str) -> Option<PathBuf> {
+    if argument.starts_with("file://") {
+        return Url::parse(argument).ok()?.to_file_path().ok();
+    }
+    if let Some(rest) = argument.strip_prefix("~/") {
+        return Some(PathBuf::from(std::env::var_os("HOME")?).join(rest));
+    }
+
+    let path = PathBuf::from(argument);
+    if path.is_absolute() {
+        return Some(path);
+    }
+    // anything else could just as well be text, so it has to exist
+    if argument.contains('\n') || !path.is_file() {
+        return None;
+    }
+    std::env::current_dir().ok().map(|d| d.join(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{local_path, looks_like_image, sniff, Content};
+
+    #[test]
+    fn bytes_win_over_the_content_type() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        assert_eq!(sniff(png, Some("text/html")), Content::Image);
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 ", None), Content::Image);
+        assert_eq!(
+            sniff(b"\xef\xbb\xbf  <!DOCTYPE html><html>", Some("image/png")),
+            Content::Html
+        );
+        assert_eq!(
+            sniff(b"<?xml version=\"1.0\"?>\n<svg xmlns=\"\">", None),
+            Content::Image
+        );
+
+        assert_eq!(
+            sniff(b"\x01\x02", Some("image/x-something; q=1")),
+            Content::Image
+        );
+        assert_eq!(sniff(b"<div>oops</div>", None), Content::Html);
+        assert_eq!(sniff(b"plain text", Some("text/plain")), Content::Unknown);
+    }
+
+    #[test]
+    fn urls_are_judged_by_their_path() {
+        assert!(looks_like_image("https://a.com/cat.webp?width=200#top"));
+        assert!(looks_like_image("https://a.com/cat.JPG"));
+        assert!(!looks_like_image("https://a.com/cat.png/view"));
+        assert!(!looks_like_image("https://cdn.a.com/images/12345"));
+        assert!(!looks_like_image("https://a.com/page?img=cat.png"));
+    }
+
+    #[test]
+    fn local_paths_are_resolved() {
+        assert_eq!(
+            local_path("file:///pictures/my%20cat.png"),
+            Some("/pictures/my cat.png".into())
+        );
+        assert_eq!(
+            local_path("/pictures/cat.png"),
+            Some("/pictures/cat.png".into())
+        );
+        if let Some(home) = std::env::var_os("HOME") {
+            assert_eq!(
+                local_path("~/cat.png"),
+                Some(std::path::Path::new(&home).join("cat.png"))
+            );
+        }
+
+        assert_eq!(
+            local_path("Cargo.toml"),
+            Some(std::env::current_dir().unwrap().join("Cargo.toml"))
+        );
+        assert_eq!(local_path("just some text"), None);
+    }
+}
@@ -0,0 +1,628 @@
+use std::collections::HashMap;
+
+use ggez::Context;
+
+use super::board::{Item, ItemId};
+use super::geometry::OrientedRect;
+use super::history::Edit;
+
+/// rect: (x, y, w, h)
+type Rect = (f32, f32, f32, f32);
+
+#[inline]
+fn overlaps(a: Rect, b: Rect) -> bool {
+    a.0 <= b.0 + b.2 && b.0 <= a.0 + a.2 && a.1 <= b.1 + b.3 && b.1 <= a.1 + a.3
+}
+
+/// whether `inner` is completely inside `outer`
+#[inline]
+fn encloses(outer: Rect, inner: Rect) -> bool {
+    inner.0 >= outer.0
+        && inner.1 >= outer.1
+        && inner.0 + inner.2 <= outer.0 + outer.2
+        && inner.1 + inner.3 <= outer.1 + outer.3
+}
+
+struct Node {
+    region: Rect,
+    depth: usize,
+    /// (id, rect) of the entries which dont fit completely inside any one child
+    entries: Vec<(usize, Rect)>,
+    children: Option<Box<[Node; 4]>>,
+}
+
+impl Node {
+    fn new(region: Rect, depth: usize) -> Self {
+        Self {
+            region,
+            depth,
+            entries: Vec::new(),
+            children: None,
+        }
+    }
+
+    /// index of the child which completely holds `r`
+    fn child_for(&self, r: Rect) -> Option<usize> {
+        self.children
+            .as_ref()?
+            .iter()
+            .position(|child| encloses(child.region, r))
+    }
+
+    fn split(&mut self) {
+        let (x, y, w, h) = self.region;
+        let (w, h) = (w / 2.0, h / 2.0);
+
+        self.children = Some(Box::new([
+            Node::new((x, y, w, h), self.depth + 1),
+            Node::new((x + w, y, w, h), self.depth + 1),
+            Node::new((x, y + h, w, h), self.depth + 1),
+            Node::new((x + w, y + h, w, h), self.depth + 1),
+        ]));
+
+        for (id, r) in std::mem::take(&mut self.entries) {
+            self.insert(id, r);
+        }
+    }
+
+    fn insert(&mut self, id: usize, r: Rect) {
+        if let Some(c) = self.child_for(r) {
+            self.children.as_mut().unwrap()[c].insert(id, r);
+            return;
+        }
+
+        self.entries.push((id, r));
+        if self.children.is_none()
+            && self.entries.len() > QuadTree::CAPACITY
+            && self.depth < QuadTree::MAX_DEPTH
+        {
+            self.split();
+        }
+    }
+
+    fn remove(&mut self, id: usize, r: Rect) -> bool {
+        if let Some(c) = self.child_for(r) {
+            return self.children.as_mut().unwrap()[c].remove(id, r);
+        }
+
+        match self.entries.iter().position(|e| e.0 == id) {
+            Some(p) => {
+                self.entries.swap_remove(p);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn renumber(&mut self, f: &impl Fn(usize) -> usize) {
+        self.entries.iter_mut().for_each(|e| e.0 = f(e.0));
+        if let Some(children) = &mut self.children {
+            children.iter_mut().for_each(|c| c.renumber(f));
+        }
+    }
+
+    fn query(&self, area: Rect, found: &mut Vec<usize>) {
+        if !overlaps(self.region, area) {
+            return;
+        }
+
+        found.extend(
+            self.entries
+                .iter()
+                .filter(|e| overlaps(e.1, area))
+                .map(|e| e.0),
+        );
+        if let Some(children) = &self.children {
+            children.iter().for_each(|c| c.query(area, found));
+        }
+    }
+}
+
+/// region quadtree of rects, entries are kept in the deepest node that completely holds them
+pub struct QuadTree {
+    root: Node,
+    /// entries which didnt fit in the root region when they were inserted
+    outside: Vec<(usize, Rect)>,
+}
+
+impl Default for QuadTree {
+    fn default() -> Self {
+        Self::new((0.0, 0.0, 0.0, 0.0))
+    }
+}
+
+impl QuadTree {
+    /// entries a node can hold before it splits
+    const CAPACITY: usize = 8;
+    const MAX_DEPTH: usize = 16;
+
+    pub fn new(region: Rect) -> Self {
+        Self {
+            root: Node::new(region, 0),
+            outside: Vec::new(),
+        }
+    }
+
+    /// tree covering every rect with each rects id being its index
+    #[cfg(test)]
+    pub fn from_rects(rects: &[Rect]) -> Self {
+        Self::from_entries(rects.iter().copied().enumerate())
+    }
+
+    /// tree covering every (id, rect)
+    pub fn from_entries(entries: impl Iterator<Item = (usize, Rect)> + Clone) -> Self {
+        let region = entries
+            .clone()
+            .map(|e| e.1)
+            .reduce(|a, b| {
+                let min = (a.0.min(b.0), a.1.min(b.1));
+                let max = ((a.0 + a.2).max(b.0 + b.2), (a.1 + a.3).max(b.1 + b.3));
+                (min.0, min.1, max.0 - min.0, max.1 - min.1)
+            })
+            .unwrap_or_default();
+
+        let mut tree = Self::new(region);
+        for (id, r) in entries {
+            tree.insert(id, r);
+        }
+
+        tree
+    }
+
+    pub fn insert(&mut self, id: usize, r: Rect) {
+        if encloses(self.root.region, r) {
+            self.root.insert(id, r)
+        } else {
+            self.outside.push((id, r))
+        }
+    }
+
+    /// `r` has to be the same rect the entry was inserted with
+    pub fn remove(&mut self, id: usize, r: Rect) -> bool {
+        if encloses(self.root.region, r) && self.root.remove(id, r) {
+            return true;
+        }
+
+        match self.outside.iter().position(|e| e.0 == id) {
+            Some(p) => {
+                self.outside.swap_remove(p);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// changes the id of every entry to `f(id)`
+    pub fn renumber(&mut self, f: impl Fn(usize) -> usize) {
+        self.outside.iter_mut().for_each(|e| e.0 = f(e.0));
+        self.root.renumber(&f);
+    }
+
+    /// how many entries are outside the roots region, which are checked one by one
+    #[inline]
+    pub fn outside(&self) -> usize {
+        self.outside.len()
+    }
+
+    /// ids of every entry touching `area` in no particular order
+    pub fn query(&self, area: Rect) -> Vec<usize> {
+        let mut found = self
+            .outside
+            .iter()
+            .filter(|e| overlaps(e.1, area))
+            .map(|e| e.0)
+            .collect();
+        self.root.query(area, &mut found);
+
+        found
+    }
+}
+
+/// an indexed item
+struct Entry {
+    id: ItemId,
+    rect: OrientedRect,
+}
+
+/// keeps track of where every item on a board is so they dont all have to be checked (or measured) every frame
+/// changes are kept up with one item at a time, only [`SpatialIndex::invalidate`] measures everything again
+#[derive(Default)]
+pub struct SpatialIndex {
+    tree: QuadTree,
+    /// each item in the same order as the items, [`None`] until its measured on the next refresh
+    entries: Vec<Option<Entry>>,
+    /// index of each item by its id
+    ids: HashMap<ItemId, usize>,
+    /// ids of every connector, which have to be rerouted whenever anything moves
+    connectors: Vec<ItemId>,
+    /// indices of the items which moved (or were added) since the last refresh
+    moved: Vec<usize>,
+    /// whether everything has to be indexed again
+    stale: bool,
+}
+
+impl SpatialIndex {
+    /// entries outside the trees region before its built again around all of them
+    const MAX_OUTSIDE: usize = 64;
+
+    pub fn new() -> Self {
+        Self {
+            stale: true,
+            ..Default::default()
+        }
+    }
+
+    /// the item at `i` has been moved, scaled or rotated (or swapped with another)
+    #[inline]
+    pub fn moved(&mut self, i: usize) {
+        self.moved.push(i);
+    }
+
+    /// the items have all changed (like after merging in a store changed elsewhere) so everything has to be indexed again
+    #[inline]
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    /// an item was inserted at `i`, its measured on the next refresh
+    pub fn inserted(&mut self, i: usize) {
+        self.entries.insert(i, None);
+        self.shift(i + 1, |j| if j >= i { j + 1 } else { j });
+        self.moved.push(i);
+    }
+
+    /// the item at `i` was removed
+    pub fn removed(&mut self, i: usize) {
+        if let Some(entry) = self.entries.remove(i) {
+            self.tree.remove(i, entry.rect.bounds());
+            self.ids.remove(&entry.id);
+            self.connectors.retain(|&c| c != entry.id);
+        }
+        self.moved.retain(|&j| j != i);
+        self.shift(i, |j| if j > i { j - 1 } else { j });
+    }
+
+    /// the items were put in a new order, the item at `i` going to `to[i]`
+    pub fn reordered(&mut self, to: &[usize]) {
+        let mut entries = std::mem::take(&mut self.entries);
+        self.entries = (0..entries.len()).map(|_| None).collect();
+        for (from, entry) in entries.drain(..).enumerate() {
+            self.entries[to[from]] = entry;
+        }
+
+        self.tree.renumber(|j| to[j]);
+        self.moved.iter_mut().for_each(|j| *j = to[*j]);
+        for (i, entry) in self.entries.iter().enumerate() {
+            if let Some(entry) = entry {
+                self.ids.insert(entry.id, i);
+            }
+        }
+    }
+
+    /// keeps up with a change to the items, `inverse` being the edit which reverses it
+    pub fn applied(&mut self, inverse: &Edit) {
+        match inverse {
+            Edit::Remove(i) => self.inserted(*i),
+            Edit::Insert(i, _) => self.removed(*i),
+            // the inverse takes each item back from where it went
+            Edit::Reorder(to) => self.reordered(to),
+            Edit::Transform(i, _)
+            | Edit::Layer(i, _)
+            | Edit::Text(i, _)
+            | Edit::Style(i, _)
+            | Edit::ShapeStyle(i, _)
+            | Edit::Routing(i, _) => self.moved(*i),
+            // the inverse of a group reverses its edits from the last one back
+            Edit::Group(edits) => edits.iter().rev().for_each(|e| self.applied(e)),
+        }
+    }
+
+    /// changes every index to `f(index)` after an item was inserted or removed, `from` being the first item which moves
+    fn shift(&mut self, from: usize, f: impl Fn(usize) -> usize) {
+        self.moved.iter_mut().for_each(|j| *j = f(*j));
+        // nothing comes after items added (or removed) on top
+        if from >= self.entries.len() {
+            return;
+        }
+        self.tree.renumber(&f);
+        for (i, entry) in self.entries.iter().enumerate().skip(from) {
+            if let Some(entry) = entry {
+                self.ids.insert(entry.id, i);
+            }
+        }
+    }
+
+    /// brings the index up to date with `items`, returning whether anything changed
+    pub fn refresh(&mut self, items: &[Item], c: &Context) -> bool {
+        self.refresh_with(items, |x| x.oriented(c))
+    }
+
+    /// [`SpatialIndex::refresh`] with `measure` giving the rect of an item
+    fn refresh_with(&mut self, items: &[Item], measure: impl Fn(&Item) -> OrientedRect) -> bool {
+        if self.stale {
+            self.entries = items
+                .iter()
+                .map(|x| {
+                    Some(Entry {
+                        id: x.id(),
+                        rect: measure(x),
+                    })
+                })
+                .collect();
+            self.ids = items.iter().enumerate().map(|(i, x)| (x.id(), i)).collect();
+            self.connectors = items
+                .iter()
+                .filter(|x| matches!(x, Item::Connector(_)))
+                .map(Item::id)
+                .collect();
+            self.rebuild();
+
+            self.stale = false;
+            self.moved.clear();
+            return true;
+        }
+
+        if self.moved.is_empty() {
+            return false;
+        }
+        let mut moved = std::mem::take(&mut self.moved);
+        moved.sort_unstable();
+        moved.dedup();
+        for i in moved {
+            match self.entries[i].take() {
+                Some(old) => _ = self.tree.remove(i, old.rect.bounds()),
+                None if matches!(items[i], Item::Connector(_)) => {
+                    self.connectors.push(items[i].id())
+                }
+                None => (),
+            }
+
+            let entry = Entry {
+                id: items[i].id(),
+                rect: measure(&items[i]),
+            };
+            self.tree.insert(i, entry.rect.bounds());
+            // swapped items are both marked as moved so this keeps up with them
+            self.ids.insert(entry.id, i);
+            self.entries[i] = Some(entry);
+        }
+
+        // whatever ended up outside the tree is checked one by one, so the tree grows to hold it
+        if self.tree.outside() > Self::MAX_OUTSIDE.max(self.entries.len() / 8) {
+            self.rebuild();
+        }
+        true
+    }
+
+    /// builds the tree again around every entry, without measuring anything
+    fn rebuild(&mut self) {
+        self.tree = QuadTree::from_entries(
+            self.entries
+                .iter()
+                .enumerate()
+                .filter_map(|(i, e)| Some((i, e.as_ref()?.rect.bounds()))),
+        );
+    }
+
+    /// index of the item with the id `id` as of the last refresh
+    #[inline]
+    pub fn position(&self, id: ItemId) -> Option<usize> {
+        self.ids.get(&id).copied()
+    }
+
+    /// ids of every connector as of the last refresh
+    #[inline]
+    pub fn connectors(&self) -> &[ItemId] {
+        &self.connectors
+    }
+
+    /// rect of the item at `i` as of the last refresh
+    #[inline]
+    pub fn rect(&self, i: usize) -> OrientedRect {
+        self.entries[i]
+            .as_ref()
+            .expect("items are measured on the refresh after theyre added")
+            .rect
+    }
+
+    /// indices of the items touching the world rect `area`, bottom to top
+    pub fn query(&self, area: Rect) -> Vec<usize> {
+        let mut found = self.tree.query(area);
+        found.sort_unstable();
+        found
+    }
+
+    /// indices of the items under the world position `p`, bottom to top
+    pub fn query_point(&self, p: (f32, f32)) -> Vec<usize> {
+        let mut found = self.query((p.0, p.1, 0.0, 0.0));
+        found.retain(|&i| self.rect(i).contains(p));
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{overlaps, Entry, QuadTree, Rect, SpatialIndex};
+    use crate::board::board::{Item, ItemId, ItemText};
+    use crate::board::geometry::OrientedRect;
+
+    /// a synthetic board of `n` rects spread over a world roughly `n` times bigger than the rects
+    fn board(n: usize) -> Vec<Rect> {
+        // simple lcg so the boards are the same every run
+        let mut seed = 0x9e3779b9u32;
+        let mut next = move || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 8) as f32 / (1 << 24) as f32
+        };
+        let size = (n as f32).sqrt() * 400.0;
+
+        (0..n)
+            .map(|_| {
+                (
+                    (next() - 0.5) * size,
+                    (next() - 0.5) * size,
+                    10.0 + next() * 500.0,
+                    10.0 + next() * 500.0,
+                )
+            })
+            .collect()
+    }
+
+    fn linear(rects: &[Rect], area: Rect) -> Vec<usize> {
+        (0..rects.len())
+            .filter(|&i| overlaps(rects[i], area))
+            .collect()
+    }
+
+    fn areas(n: usize) -> Vec<Rect> {
+        let size = (n as f32).sqrt() * 400.0;
+        (0..50)
+            .map(|i| {
+                let t = i as f32 / 50.0 - 0.5;
+                (t * size, -t * size * 0.7, 1920.0, 1080.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn query_matches_linear_scan() {
+        let rects = board(2000);
+        let tree = QuadTree::from_rects(&rects);
+
+        for area in areas(2000).into_iter().chain([(0.0, 0.0, 0.0, 0.0)]) {
+            let mut found = tree.query(area);
+            found.sort_unstable();
+            assert_eq!(found, linear(&rects, area));
+        }
+    }
+
+    #[test]
+    fn moving_entries() {
+        let mut rects = board(500);
+        let mut tree = QuadTree::from_rects(&rects);
+
+        for i in (0..rects.len()).step_by(3) {
+            assert!(tree.remove(i, rects[i]));
+            rects[i] = (
+                rects[i].0 * 3.0,
+                rects[i].1 - 1000.0,
+                rects[i].2,
+                rects[i].3,
+            );
+            // some of these end up outside the roots region
+            tree.insert(i, rects[i]);
+        }
+        assert!(!tree.remove(rects.len(), (0.0, 0.0, 1.0, 1.0)));
+
+        for area in areas(500) {
+            let mut found = tree.query(area);
+            found.sort_unstable();
+            assert_eq!(found, linear(&rects, area));
+        }
+    }
+
+    #[test]
+    fn renumbering_entries() {
+        let mut rects = board(500);
+        let mut tree = QuadTree::from_rects(&rects);
+
+        // like an item being inserted at 100
+        rects.insert(100, (0.0, 0.0, 1.0, 1.0));
+        tree.renumber(|i| if i >= 100 { i + 1 } else { i });
+        tree.insert(100, rects[100]);
+
+        for area in areas(500) {
+            let mut found = tree.query(area);
+            found.sort_unstable();
+            assert_eq!(found, linear(&rects, area));
+        }
+    }
+
+    #[test]
+    fn keeping_up_with_items_without_measuring_them_again() {
+        let rects = [
+            (0.0, 0.0, 10.0, 10.0),
+            (100.0, 0.0, 10.0, 10.0),
+            (200.0, 0.0, 10.0, 10.0),
+        ];
+        let mut index = SpatialIndex {
+            entries: rects
+                .iter()
+                .enumerate()
+                .map(|(i, &r)| {
+                    Some(Entry {
+                        id: i as ItemId,
+                        rect: OrientedRect::new(r, 0.0),
+                    })
+                })
+                .collect(),
+            ids: (0..rects.len()).map(|i| (i as ItemId, i)).collect(),
+            ..Default::default()
+        };
+        index.rebuild();
+        let at = |x| (x, 5.0, 1.0, 1.0);
+
+        index.removed(0);
+        assert_eq!((index.position(0), index.position(2)), (None, Some(1)));
+        assert_eq!(index.query(at(205.0)), [1]);
+
+        // waits for the next refresh to be measured
+        index.inserted(0);
+        assert_eq!(index.position(2), Some(2));
+        assert_eq!(index.query(at(205.0)), [2]);
+        assert_eq!(index.moved, [0]);
+
+        index.reordered(&[2, 0, 1]);
+        assert_eq!((index.position(1), index.position(2)), (Some(0), Some(1)));
+        assert_eq!(index.query(at(105.0)), [0]);
+        assert_eq!(index.moved, [2]);
+    }
+
+    #[test]
+    fn catching_up_with_fewer_items() {
+        let text = |id| Item::Text(ItemText::new(String::new())).with_id(id);
+        let measure = |x: &Item| OrientedRect::new((x.id() as f32 * 100.0, 0.0, 10.0, 10.0), 0.0);
+        let mut index = SpatialIndex::new();
+        index.refresh_with(&[text(0), text(1), text(2)], measure);
+        assert_eq!(index.query((0.0, 0.0, 300.0, 10.0)), [0, 1, 2]);
+
+        // like a store changed elsewhere being merged in with only the last item left
+        index.invalidate();
+        index.refresh_with(&[text(2)], measure);
+        assert_eq!(index.query((0.0, 0.0, 300.0, 10.0)), [0]);
+        assert_eq!((index.position(0), index.position(2)), (None, Some(0)));
+        assert_eq!(index.rect(0).bounds(), (200.0, 0.0, 10.0, 10.0));
+    }
+
+    /// run with `cargo test --release -- --ignored --nocapture bench`
+    #[test]
+    #[ignore]
+    fn bench_large_boards() {
+        use std::time::Instant;
+
+        for n in [1_000, 10_000, 100_000, 1_000_000] {
+            let rects = board(n);
+            let areas = areas(n);
+
+            let start = Instant::now();
+            let tree = QuadTree::from_rects(&rects);
+            let build = start.elapsed();
+
+            let start = Instant::now();
+            let indexed = areas.iter().map(|a| tree.query(*a).len()).sum::<usize>();
+            let query = start.elapsed() / areas.len() as u32;
+
+            let start = Instant::now();
+            let scanned = areas
+                .iter()
+                .map(|a| linear(&rects, *a).len())
+                .sum::<usize>();
+            let scan = start.elapsed() / areas.len() as u32;
+
+            assert_eq!(indexed, scanned);
+            println!(
+                "{n:>9} items: build {build:>10.2?}, viewport query {query:>10.2?}, linear scan {scan:>10.2?}"
+            );
+        }
+    }
+}
@@ -1,84 +1,392 @@
+use std::collections::HashSet;
 use std::io::Write;
 
 use std::fs::{File, OpenOptions};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use super::board;
+use super::cache::Cache;
+use super::schema::{self, Header};
 
 pub struct Store {
     /// file path listing all the items
-    pub store: File,
-    /// cache directory path with images of all cached items (name corrosponds to url)
-    pub cache: PathBuf,
+    pub store: PathBuf,
+    /// images of all cached items
+    pub cache: Cache,
+    /// file path of the boards layers
+    pub layers: PathBuf,
+    /// directory path with timestamped copies of older stores
+    pub backups: PathBuf,
+    /// how many backups are kept around, older ones being removed when saving
+    pub keep_backups: usize,
+    /// how long after the newest backup another one is made, so autosaves dont fill the backups with the last few minutes
+    pub backup_every: Duration,
+    /// file path of the lines which couldnt be read, so saving doesnt lose them
+    pub quarantine: PathBuf,
+    /// when the store was last changed and its size as of the last read or save
+    /// to notice when something else changes it
+    stamp: Option<(SystemTime, u64)>,
 }
 
 impl Store {
-    pub fn create<P>(store_path: P) -> std::io::Result<Self>
+    pub fn create<P>(store_path: P, keep_backups: usize) -> std::io::Result<Self>
     where
         P: AsRef<Path>,
     {
         let store_path = store_path.as_ref();
         let cache_path = store_path.join(".cache");
-        _ = std::fs::create_dir(&store_path);
+        let backups_path = store_path.join("backups");
+        _ = std::fs::create_dir(store_path);
         _ = std::fs::create_dir(&cache_path);
+        _ = std::fs::create_dir(&backups_path);
 
         let store_file_path = store_path.join("store.store");
-        if let Err(_) = File::create_new(&store_file_path) {
-            std::fs::copy(&store_file_path, store_path.join("backup.store"))?;
-        }
+        _ = File::create_new(&store_file_path);
 
         Ok(Self {
-            store: OpenOptions::new()
-                .read(true)
-                .write(true)
-                .append(true)
-                .open(&store_file_path)?,
-            cache: cache_path,
+            store: store_file_path,
+            cache: Cache::open(cache_path),
+            layers: store_path.join("layers.store"),
+            backups: backups_path,
+            keep_backups,
+            backup_every: Duration::from_secs(10 * 60),
+            quarantine: store_path.join("quarantine.store"),
+            stamp: None,
         })
     }
 
-    pub fn clear(&mut self) -> std::io::Result<()> {
-        self.store.set_len(0)
+    /// contents of the newest store which can be read, going through the backups if the store
+    /// itself cant be read or is empty
+    /// lines which arent items (like one cut short) are left for [`Store::read_items`] to quarantine
+    pub fn recover(&mut self) -> std::io::Result<String> {
+        self.stamp = self.current_stamp();
+        let store = std::fs::read_to_string(&self.store);
+        match &store {
+            Ok(contents) if !contents.trim().is_empty() => return store,
+            // a new board
+            Ok(_) if self.list_backups()?.is_empty() => return store,
+            Ok(_) => println!("the store is empty, looking through the backups"),
+            Err(e) => println!("couldnt read the store, looking through the backups: {e}"),
+        }
+
+        for backup in self.list_backups()?.into_iter().rev() {
+            match std::fs::read_to_string(&backup) {
+                Ok(contents) if Self::intact(&contents) => {
+                    println!("recovered the board from {}", backup.display());
+                    return Ok(contents);
+                }
+                Ok(_) => println!("backup {} is broken too", backup.display()),
+                Err(e) => println!("couldnt read backup {}: {e}", backup.display()),
+            }
+        }
+
+        println!("no backups could be read, keeping the store as it is");
+        store
+    }
+
+    /// whether `contents` are a whole store: not empty and not cut off partway through a line
+    /// only these are backed up, and used when recovering from a backup
+    #[inline]
+    fn intact(contents: &str) -> bool {
+        !contents.trim().is_empty() && contents.ends_with('\n')
+    }
+
+    /// reads the items of a store, upgrading them from older versions
+    /// lines which cant be read are moved to the quarantine
+    pub fn read_items(
+        &mut self,
+        contents: &str,
+        c: &ggez::Context,
+    ) -> std::io::Result<Vec<board::Item>> {
+        Ok(self
+            .parse(contents)?
+            .into_iter()
+            .map(|i| self.load(i, c))
+            .collect())
+    }
+
+    /// the items in the lines of `contents` without anything which isnt saved, like images
+    fn parse(&self, contents: &str) -> std::io::Result<Vec<board::Item>> {
+        let (version, lines) = schema::split_header(contents)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        if version != schema::VERSION {
+            println!("upgrading the store from version {version}");
+        }
+
+        let mut items = Vec::with_capacity(lines.len());
+        // read once theres a bad line
+        let mut quarantined = None;
+        for line in lines {
+            match schema::migrate(line, version) {
+                Ok(i) => items.push(i),
+                Err(e) => {
+                    let quarantined = quarantined.get_or_insert_with(|| self.quarantined());
+                    // the store is read again on every reload, so its likely been quarantined already
+                    if !quarantined.contains(line) {
+                        println!("line {line} couldnt be read, quarantining it: {e}");
+                        self.quarantine(line, version, &e)?;
+                        quarantined.insert(line.to_owned());
+                    }
+                }
+            }
+        }
+
+        Ok(items)
     }
 
-    pub fn read_line(&self, line: &str, c: &ggez::Context) -> Result<board::Item, String> {
+    /// the lines already in the quarantine
+    fn quarantined(&self) -> HashSet<String> {
+        let Ok(contents) = std::fs::read_to_string(&self.quarantine) else {
+            return HashSet::new();
+        };
+        contents
+            .lines()
+            .filter_map(|entry| {
+                let entry = serde_json::from_str::<serde_json::Value>(entry).ok()?;
+                Some(entry.get("line")?.as_str()?.to_owned())
+            })
+            .collect()
+    }
+
+    /// appends a line to the quarantine along with why it couldnt be read
+    fn quarantine(&self, line: &str, version: u32, error: &str) -> std::io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.quarantine)?;
+        let entry = serde_json::json!({ "version": version, "error": error, "line": line });
+        writeln!(file, "{entry}")
+    }
+
+    /// loads whatever an item needs which isnt saved (like images)
+    fn load(&mut self, item: board::Item, c: &ggez::Context) -> board::Item {
         use board::Item;
 
-        Ok(
-            match serde_json::from_str(line).or(Err("from_str failed"))? {
-                Item::Text(i) => Item::Text(i),
-                Item::Image(i) => Item::Image(
-                    if self.is_cached(board::Board::name_from_path(i.kind.argument())) {
-                        board::ItemImage::from_path(self, i.kind.argument(), c)
-                            .or(Err("failed to load image from path"))?
-                    } else {
-                        board::ItemImage::image_from_url(self, i.kind.argument(), c)
-                            .or(Err("image_from_url failed"))?
-                    },
-                )
-                .with_position(i.position)
-                .with_scale(i.scale)
-                .with_rotation(i.rotation),
-            },
-        )
+        match item {
+            Item::Image(i) => Item::Image({
+                let source = i.kind.argument();
+                match self.load_image(&i.blob, source, c) {
+                    Ok(Some(image)) => image,
+                    // downloaded in the background
+                    Ok(None) => board::ItemImage::placeholder(source),
+                    // kept so it isnt lost on the next save, it can be retried or relinked later
+                    Err(e) => {
+                        println!("couldnt load {source}, keeping it as a placeholder: {e}");
+                        let mut image = board::ItemImage::placeholder(source);
+                        image.blob = i.blob.clone();
+                        image.failed = Some(e);
+                        image
+                    }
+                }
+            })
+            .with_id(i.id)
+            .with_layer(i.layer)
+            .with_position(i.position)
+            .with_scale(i.scale)
+            .with_rotation(i.rotation),
+            i => i,
+        }
     }
 
-    #[inline]
-    pub fn add(&mut self, item: &board::Item) -> std::io::Result<()> {
-        writeln!(self.store, "{}", serde_json::to_string(item).unwrap())
+    /// the image at `source` from the cache (or the file system for local ones)
+    /// [`None`] if it needs downloading
+    fn load_image(
+        &mut self,
+        blob: &str,
+        source: &str,
+        c: &ggez::Context,
+    ) -> Result<Option<board::ItemImage>, String> {
+        // cached before blobs existed, under the last part of its source
+        let name = board::Board::name_from_path(source);
+        let blob = if self.cache.contains(blob) {
+            Some(blob.to_owned())
+        } else {
+            self.cache.adopt(source, name).map_err(|e| e.to_string())?
+        };
+
+        match blob {
+            Some(blob) => board::ItemImage::from_blob(self, blob, source, c)
+                .map(Some)
+                .map_err(|e| format!("failed to load image from the cache: {e}")),
+            None if source.starts_with("http") => Ok(None),
+            None => board::ItemImage::from_source(self, source, c)
+                .map(Some)
+                .map_err(|e| e.to_string()),
+        }
     }
 
-    #[inline]
-    pub fn is_cached(&self, name: &str) -> bool {
-        self.cache.join(name).exists()
+    /// replaces the store with `items`, backing up the old one first
+    /// nothing is written if any item cant be serialised
+    pub fn save(&mut self, items: &[board::Item]) -> std::io::Result<()> {
+        let mut contents = serde_json::to_string(&Header::current())? + "\n";
+        for item in items {
+            contents += &serde_json::to_string(item)?;
+            contents.push('\n');
+        }
+
+        self.backup()?;
+        write_atomic(&self.store, contents.as_bytes())?;
+        self.stamp = self.current_stamp();
+
+        Ok(())
     }
 
     #[inline]
-    pub fn remove_cached(&self, name: &str) -> std::io::Result<()> {
-        if self.is_cached(name) {
-            std::fs::remove_file(self.cache.join(name))
-        } else {
-            Ok(())
+    fn current_stamp(&self) -> Option<(SystemTime, u64)> {
+        let metadata = std::fs::metadata(&self.store).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+
+    /// whether something else changed the store since it was last read or saved
+    pub fn changed_elsewhere(&self) -> bool {
+        self.current_stamp()
+            .is_some_and(|stamp| Some(stamp) != self.stamp)
+    }
+
+    /// copies the current store into the backups if the newest one is older than `backup_every`,
+    /// removing the oldest ones past `keep_backups`
+    /// stores which arent whole (like ones [`Store::recover`] didnt use) arent worth backing up
+    fn backup(&self) -> std::io::Result<()> {
+        if self.keep_backups == 0 {
+            return Ok(());
+        }
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let newest = self.list_backups()?.last().and_then(|b| backup_millis(b));
+        if newest
+            .is_some_and(|newest| millis.saturating_sub(newest) < self.backup_every.as_millis())
+        {
+            return Ok(());
+        }
+        let contents = match std::fs::read_to_string(&self.store) {
+            Ok(contents) if Self::intact(&contents) => contents,
+            _ => return Ok(()),
+        };
+
+        write_atomic(
+            self.backups.join(format!("store-{millis:015}.store")),
+            contents.as_bytes(),
+        )?;
+
+        let backups = self.list_backups()?;
+        for old in &backups[..backups.len().saturating_sub(self.keep_backups)] {
+            std::fs::remove_file(old)?;
+        }
+
+        Ok(())
+    }
+
+    /// oldest to newest
+    fn list_backups(&self) -> std::io::Result<Vec<PathBuf>> {
+        let mut backups = std::fs::read_dir(&self.backups)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with("store-") && n.ends_with(".store"))
+            })
+            .collect::<Vec<PathBuf>>();
+        // the timestamps are zero padded so this sorts by time
+        backups.sort();
+
+        Ok(backups)
+    }
+}
+
+/// when the backup at `path` was made, in milliseconds since the epoch
+fn backup_millis(path: &Path) -> Option<u128> {
+    let name = path.file_name()?.to_str()?;
+    name.strip_prefix("store-")?
+        .strip_suffix(".store")?
+        .parse()
+        .ok()
+}
+
+/// writes `contents` to a temporary file next to `path` then renames it over `path`
+/// so a crash leaves either the old file or the new one, never half of one
+pub fn write_atomic<P: AsRef<Path>>(path: P, contents: &[u8]) -> std::io::Result<()> {
+    let path = path.as_ref();
+    // unique so files written at once from several threads (like thumbnails) dont share one
+    static WRITES: AtomicU64 = AtomicU64::new(0);
+    let mut temp_name = path.file_name().unwrap_or_default().to_owned();
+    temp_name.push(format!(
+        ".{}-{}.tmp",
+        std::process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp = path.with_file_name(temp_name);
+
+    let mut file = File::create(&temp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+
+    std::fs::rename(&temp, path)?;
+    // makes the rename itself durable, not every platform can open directories
+    if let Some(dir) = path.parent().and_then(|d| File::open(d).ok()) {
+        _ = dir.sync_all();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Store;
+
+    #[test]
+    fn saving_rotates_backups_and_recovers_from_them() {
+        let path = std::env::temp_dir().join(format!("board-store-test-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&path);
+        let mut store = Store::create(&path, 2).unwrap();
+        store.backup_every = std::time::Duration::ZERO;
+
+        let item = r#"{"Text":{"text":"hi","position":[0.0,0.0],"scale":1.0,"rotation":0.0}}"#;
+        for i in 0..4 {
+            super::write_atomic(&store.store, format!("{item}\n").repeat(i).as_bytes()).unwrap();
+            store.save(&[]).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
         }
+        // the empty store isnt worth backing up
+        assert_eq!(store.list_backups().unwrap().len(), 2);
+        assert_eq!(store.recover().unwrap(), "{\"version\":1}\n");
+        assert!(!store.changed_elsewhere());
+
+        // bad lines are quarantined without losing the rest
+        let broken = format!("{item}\n{{not an item\n{item}\n");
+        std::fs::write(&store.store, &broken).unwrap();
+        assert!(store.changed_elsewhere());
+        assert_eq!(store.recover().unwrap(), broken);
+        assert_eq!(store.parse(&broken).unwrap().len(), 2);
+        // only once however often its read
+        assert_eq!(store.parse(&broken).unwrap().len(), 2);
+        let quarantine = store.quarantine.clone();
+        let quarantined = || std::fs::read_to_string(&quarantine).unwrap();
+        assert_eq!(quarantined().matches("{not an item").count(), 1);
+
+        // a store cut short keeps what it has, the cut off line being quarantined
+        let cut = &broken[..broken.len() - 10];
+        std::fs::write(&store.store, cut).unwrap();
+        assert_eq!(store.recover().unwrap(), cut);
+        assert_eq!(store.parse(cut).unwrap().len(), 1);
+        assert_eq!(quarantined().lines().count(), 2);
+
+        // an emptied store is replaced by the newest backup
+        std::fs::write(&store.store, "").unwrap();
+        assert_eq!(store.recover().unwrap(), format!("{item}\n").repeat(3));
+
+        // broken stores arent backed up, and backups are only made once in a while
+        let newest = store.list_backups().unwrap();
+        store.save(&[]).unwrap();
+        assert_eq!(store.list_backups().unwrap(), newest);
+        store.backup_every = std::time::Duration::from_secs(60);
+        store.save(&[]).unwrap();
+        assert_eq!(store.list_backups().unwrap(), newest);
+
+        std::fs::remove_dir_all(&path).unwrap();
     }
 }
@@ -0,0 +1,346 @@
+use ggez::graphics::{self, Color, Text, TextAlign, TextFragment, TextLayout};
+use ggez::Context;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum FontFamily {
+    /// ggezs built in font
+    #[default]
+    Default,
+    Sans,
+    /// MeowScript
+    Fancy,
+}
+
+impl FontFamily {
+    /// adds every font a family can use to ggez
+    pub fn add_fonts(ctx: &mut Context) {
+        for (name, path) in [
+            ("fancy font", "/fonts/MeowScript-Regular.ttf"),
+            ("sans", "/fonts/DejaVuSans.ttf"),
+            ("sans bold", "/fonts/DejaVuSans-Bold.ttf"),
+            ("sans italic", "/fonts/DejaVuSans-Oblique.ttf"),
+            ("sans bold italic", "/fonts/DejaVuSans-BoldOblique.ttf"),
+        ] {
+            match graphics::FontData::from_path(ctx, path) {
+                Ok(font) => ctx.gfx.add_font(name, font),
+                Err(e) => println!("couldnt load font {path}: {e}"),
+            }
+        }
+    }
+
+    /// name of the font added to ggez, [`None`] being ggezs default font
+    /// families without a bold or italic font use the sans ones for those
+    fn font(self, bold: bool, italic: bool) -> Option<&'static str> {
+        match (self, bold, italic) {
+            (FontFamily::Default, false, false) => None,
+            (FontFamily::Fancy, false, false) => Some("fancy font"),
+            (_, false, false) => Some("sans"),
+            (_, true, false) => Some("sans bold"),
+            (_, false, true) => Some("sans italic"),
+            (_, true, true) => Some("sans bold italic"),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            FontFamily::Default => FontFamily::Sans,
+            FontFamily::Sans => FontFamily::Fancy,
+            FontFamily::Fancy => FontFamily::Default,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Align {
+    #[default]
+    Left,
+    Centre,
+    Right,
+}
+
+impl Align {
+    pub fn next(self) -> Self {
+        match self {
+            Align::Left => Align::Centre,
+            Align::Centre => Align::Right,
+            Align::Right => Align::Left,
+        }
+    }
+
+    /// how far right of the items left edge the text is drawn from, `width` being the widest line
+    /// (ggez lines up each line with that point instead of inside a box)
+    pub fn offset(self, width: f32) -> f32 {
+        match self {
+            Align::Left => 0.0,
+            Align::Centre => width / 2.0,
+            Align::Right => width,
+        }
+    }
+}
+
+/// how a text item looks
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TextStyle {
+    pub font: FontFamily,
+    /// rgb, [`None`] uses the boards text colour
+    pub colour: Option<[u8; 3]>,
+    pub align: Align,
+    /// width lines wrap at, in multiples of the texts scale so scaling doesnt rewrap
+    pub wrap: Option<f32>,
+    /// whether **bold** and *italic* spans are parsed
+    pub markdown: bool,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            font: FontFamily::default(),
+            colour: None,
+            align: Align::default(),
+            wrap: None,
+            markdown: true,
+        }
+    }
+}
+
+/// colours styles cycle through, [`None`] being the boards text colour (or no fill)
+const COLOURS: [Option<[u8; 3]>; 6] = [
+    None,
+    Some([214, 69, 65]),
+    Some([230, 145, 56]),
+    Some([92, 163, 91]),
+    Some([65, 128, 214]),
+    Some([145, 92, 186]),
+];
+
+#[inline]
+fn colour(rgb: [u8; 3]) -> Color {
+    Color::from_rgb(rgb[0], rgb[1], rgb[2])
+}
+
+impl TextStyle {
+    const WRAPS: [Option<f32>; 5] = [None, Some(5.0), Some(10.0), Some(20.0), Some(40.0)];
+
+    /// how text items were before they could be styled
+    pub fn plain() -> Self {
+        Self {
+            markdown: false,
+            ..Default::default()
+        }
+    }
+
+    pub fn next_colour(&mut self) {
+        self.colour = next_of(&COLOURS, self.colour);
+    }
+
+    pub fn next_wrap(&mut self) {
+        self.wrap = next_of(&Self::WRAPS, self.wrap);
+    }
+
+    /// `s` laid out in this style at the pixel scale `scale`
+    /// `markdown` being false shows the markers as they are (like while typing)
+    pub fn text(&self, s: &str, scale: f32, markdown: bool) -> Text {
+        let spans = if self.markdown && markdown {
+            spans(s)
+        } else {
+            vec![Span {
+                text: s.to_owned(),
+                ..Default::default()
+            }]
+        };
+
+        let mut text = Text::default();
+        for span in spans {
+            let mut fragment = TextFragment::new(span.text);
+            if let Some(font) = self.font.font(span.bold, span.italic) {
+                fragment = fragment.font(font);
+            }
+            if let Some(rgb) = self.colour {
+                fragment = fragment.color(colour(rgb));
+            }
+            text.add(fragment);
+        }
+
+        text.set_scale(scale).set_layout(TextLayout {
+            h_align: match self.align {
+                Align::Left => TextAlign::Begin,
+                Align::Centre => TextAlign::Middle,
+                Align::Right => TextAlign::End,
+            },
+            v_align: TextAlign::Begin,
+        });
+        if let Some(wrap) = self.wrap {
+            text.set_bounds([wrap * scale, f32::INFINITY]);
+        }
+
+        text
+    }
+}
+
+/// how a shape or pen stroke looks
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShapeStyle {
+    /// rgb, [`None`] uses the boards text colour
+    pub stroke: Option<[u8; 3]>,
+    /// rgb, [`None`] leaves it unfilled
+    pub fill: Option<[u8; 3]>,
+    /// in world units
+    pub width: f32,
+}
+
+impl Default for ShapeStyle {
+    fn default() -> Self {
+        Self {
+            stroke: None,
+            fill: None,
+            width: 4.0,
+        }
+    }
+}
+
+impl ShapeStyle {
+    const WIDTHS: [f32; 5] = [2.0, 4.0, 8.0, 16.0, 32.0];
+
+    pub fn next_stroke(&mut self) {
+        self.stroke = next_of(&COLOURS, self.stroke);
+    }
+
+    pub fn next_fill(&mut self) {
+        self.fill = next_of(&COLOURS, self.fill);
+    }
+
+    pub fn next_width(&mut self) {
+        self.width = next_of(&Self::WIDTHS, self.width);
+    }
+
+    /// `text` being the boards text colour
+    pub fn stroke_colour(&self, text: Color) -> Color {
+        self.stroke.map_or(text, colour)
+    }
+
+    pub fn fill_colour(&self) -> Option<Color> {
+        self.fill.map(colour)
+    }
+}
+
+/// the option after `current` in `options`, wrapping around
+fn next_of<T: PartialEq + Copy>(options: &[T], current: T) -> T {
+    let i = options.iter().position(|&o| o == current).unwrap_or(0);
+    options[(i + 1) % options.len()]
+}
+
+/// part of a text with the same styling
+#[derive(Debug, Default, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+/// splits `text` by a small subset of markdown: `**bold**`, `*italic*` and `\` escaping either
+/// markers which never get closed are left as they are
+pub fn spans(text: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut current = Span::default();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(escaped) = rest.strip_prefix('\\').and_then(|r| r.chars().next()) {
+            if escaped == '*' || escaped == '\\' {
+                current.text.push(escaped);
+                rest = &rest[1 + escaped.len_utf8()..];
+                continue;
+            }
+        }
+
+        let marker = if rest.starts_with("**") { "**" } else { "*" };
+        if c == '*' {
+            let after = &rest[marker.len()..];
+            let open = if marker == "**" {
+                current.bold
+            } else {
+                current.italic
+            };
+            // an opening marker has to be right before the text and closed later on
+            let opens = !after.starts_with(char::is_whitespace) && closes(after, marker);
+
+            if open || opens {
+                let (bold, italic) = (current.bold, current.italic);
+                if !current.text.is_empty() {
+                    spans.push(std::mem::take(&mut current));
+                }
+                current.bold = bold ^ (marker == "**");
+                current.italic = italic ^ (marker == "*");
+
+                rest = after;
+                continue;
+            }
+        }
+
+        current.text.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    if !current.text.is_empty() {
+        spans.push(current);
+    }
+    spans
+}
+
+/// whether `marker` shows up in `rest` without being escaped
+fn closes(rest: &str, marker: &str) -> bool {
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => _ = chars.next(),
+            _ if rest[i..].starts_with(marker) => return true,
+            _ => (),
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{spans, Span};
+
+    fn span(text: &str, bold: bool, italic: bool) -> Span {
+        Span {
+            text: text.to_owned(),
+            bold,
+            italic,
+        }
+    }
+
+    #[test]
+    fn bold_and_italic() {
+        assert_eq!(
+            spans("a **bold** and *italic* ***both***"),
+            [
+                span("a ", false, false),
+                span("bold", true, false),
+                span(" and ", false, false),
+                span("italic", false, true),
+                span(" ", false, false),
+                span("both", true, true),
+            ]
+        );
+        assert_eq!(
+            spans("**bold *and italic***"),
+            [span("bold ", true, false), span("and italic", true, true)]
+        );
+    }
+
+    #[test]
+    fn unclosed_and_escaped_markers_stay() {
+        assert_eq!(spans("2 * 3 * 4"), [span("2 * 3 * 4", false, false)]);
+        assert_eq!(spans("**not bold"), [span("**not bold", false, false)]);
+        assert_eq!(
+            spans(r"\*not italic\* \\"),
+            [span(r"*not italic* \", false, false)]
+        );
+        assert_eq!(spans(""), []);
+    }
+}
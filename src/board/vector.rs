@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use ggez::graphics::{Image, ImageFormat};
+use ggez::Context;
+use resvg::{tiny_skia, usvg};
+
+use super::sniff;
+
+/// rasterised images are never bigger than this on either side
+const MAX_SIDE: f32 = 4096.0;
+
+/// an svg image, drawn again at a new resolution as the zoom changes so it stays sharp
+#[derive(Clone)]
+pub struct Vector {
+    tree: Arc<usvg::Tree>,
+    /// pixels per svg unit it was last rasterised at
+    scale: f32,
+    image: Image,
+}
+
+impl std::fmt::Debug for Vector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Vector({:?} at {}x)", self.size(), self.scale)
+    }
+}
+
+impl Vector {
+    /// the svg in `bytes` rasterised at its own size, [`None`] if it isnt an svg
+    pub fn decode(ctx: &Context, bytes: &[u8]) -> Result<Option<Self>, usvg::Error> {
+        if !sniff::is_svg(bytes) {
+            return Ok(None);
+        }
+
+        let tree = Arc::new(usvg::Tree::from_data(bytes, &usvg::Options::default())?);
+        let scale = quantise(1.0, (tree.size().width(), tree.size().height()));
+        let image = rasterise(ctx, &tree, scale);
+        Ok(Some(Self { tree, scale, image }))
+    }
+
+    /// size in svg units
+    #[inline]
+    pub fn size(&self) -> (f32, f32) {
+        (self.tree.size().width(), self.tree.size().height())
+    }
+
+    #[inline]
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// the svg rasterised at its own size as png, for apps which dont take svgs
+    pub fn to_png(&self) -> image::ImageResult<Vec<u8>> {
+        let (rgba, width, height) = pixels(&self.tree, quantise(1.0, self.size()));
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::from_raw(width, height, rgba)
+            .expect("theres a pixel for each of width by height")
+            .write_to(&mut png, image::ImageFormat::Png)?;
+        Ok(png.into_inner())
+    }
+
+    /// rasterises it again if its drawn at `scale` pixels per svg unit and the last raster doesnt suit that
+    /// returns whether it was rasterised again
+    pub fn refresh(&mut self, ctx: &Context, scale: f32) -> bool {
+        let scale = quantise(scale, self.size());
+        if scale == self.scale {
+            return false;
+        }
+
+        self.image = rasterise(ctx, &self.tree, scale);
+        self.scale = scale;
+        true
+    }
+}
+
+/// rounds `scale` up to the next half power of 2, so zooming a little doesnt rasterise it each frame
+/// and its never blurry, but without going over [`MAX_SIDE`] for an svg of `size`
+fn quantise(scale: f32, size: (f32, f32)) -> f32 {
+    let steps = (scale.max(f32::EPSILON).log2() * 2.0).ceil();
+    let largest = MAX_SIDE / size.0.max(size.1).max(1.0);
+    2f32.powf(steps / 2.0).min(largest)
+}
+
+/// the `tree` drawn at `scale` as straight (not premultiplied) rgba with its width and height
+fn pixels(tree: &usvg::Tree, scale: f32) -> (Vec<u8>, u32, u32) {
+    let size = tree.size();
+    let width = (size.width() * scale).ceil().max(1.0) as u32;
+    let height = (size.height() * scale).ceil().max(1.0) as u32;
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).expect("the size isnt 0");
+    resvg::render(
+        tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    let rgba = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+    (rgba, width, height)
+}
+
+fn rasterise(ctx: &Context, tree: &usvg::Tree, scale: f32) -> Image {
+    let (rgba, width, height) = pixels(tree, scale);
+    Image::from_pixels(ctx, &rgba, ImageFormat::Rgba8UnormSrgb, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use resvg::usvg;
+
+    use super::{pixels, quantise};
+
+    #[test]
+    fn scales_are_rounded_up_in_steps() {
+        assert_eq!(quantise(1.0, (100.0, 50.0)), 1.0);
+        assert_eq!(quantise(1.2, (100.0, 50.0)), 2f32.sqrt());
+        assert_eq!(quantise(1.5, (100.0, 50.0)), 2.0);
+        assert_eq!(quantise(0.3, (100.0, 50.0)), 2f32.sqrt() / 4.0);
+        // never bigger than the limit
+        assert_eq!(quantise(100.0, (1024.0, 10.0)), 4.0);
+    }
+
+    #[test]
+    fn svgs_are_drawn_at_the_scale() {
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="5">
+            <rect width="5" height="5" fill="#ff0000"/>
+        </svg>"##;
+        let tree = usvg::Tree::from_data(svg, &usvg::Options::default()).unwrap();
+
+        let (rgba, width, height) = pixels(&tree, 4.0);
+        assert_eq!((width, height), (40, 20));
+        // red on the left, see through on the right
+        assert_eq!(rgba[..4], [255, 0, 0, 255]);
+        assert_eq!(rgba[(width as usize - 1) * 4 + 3], 0);
+    }
+}
@@ -1,5 +1,6 @@
 #[derive(Clone, Copy)]
 pub struct Camera {
+    /// world position in the middle of the screen
     pub centre: (f32, f32), // x, y
     pub zoom: f32,
     pub resolution: (f32, f32), // w, h
@@ -17,42 +18,162 @@ impl Camera {
 }
 
 impl Camera {
+    /// zoom cant go to 0 otherwise screen coords couldnt be turned back into world coords
+    const MIN_ZOOM: f32 = 0.01;
+
     // matches ggez::event::EventHandler
     pub fn mouse_wheel_event(
         &mut self,
-        _ctx: &mut ggez::Context,
+        ctx: &mut ggez::Context,
         _x: f32,
         y: f32,
     ) -> Result<(), ggez::GameError> {
-        Ok(self.add_zoom(y / 100.0))
+        self.add_zoom_at(y / 100.0, (ctx.mouse.position().x, ctx.mouse.position().y));
+        Ok(())
     }
 
     pub fn add_zoom(&mut self, by: f32) {
         self.zoom += by;
-        self.zoom = self.zoom.max(0.0);
+        self.zoom = self.zoom.max(Self::MIN_ZOOM);
+    }
+
+    /// zooms while keeping whatever is at the screen position `anchor` in place
+    pub fn add_zoom_at(&mut self, by: f32, anchor: (f32, f32)) {
+        let world = self.screen_to_world(anchor);
+        self.add_zoom(by);
+
+        self.centre = (
+            world.0 - (anchor.0 - self.resolution.0 / 2.0) / self.zoom,
+            world.1 - (anchor.1 - self.resolution.1 / 2.0) / self.zoom,
+        );
     }
 
     pub fn screen_to_world(&self, p: (f32, f32)) -> (f32, f32) {
-        (p.0 + self.centre.0, p.1 + self.centre.1)
+        (
+            (p.0 - self.resolution.0 / 2.0) / self.zoom + self.centre.0,
+            (p.1 - self.resolution.1 / 2.0) / self.zoom + self.centre.1,
+        )
     }
 
     pub fn world_to_screen(&self, p: (f32, f32)) -> (f32, f32) {
-        let view_offset = (p.0 - self.centre.0, p.1 - self.centre.1);
-        let view_offset = (view_offset.0 * self.zoom, view_offset.1 * self.zoom);
+        (
+            (p.0 - self.centre.0) * self.zoom + self.resolution.0 / 2.0,
+            (p.1 - self.centre.1) * self.zoom + self.resolution.1 / 2.0,
+        )
+    }
+
+    /// world rect `r` on screen
+    /// rect: (x, y, w, h)
+    pub fn rect_to_screen(&self, r: (f32, f32, f32, f32)) -> (f32, f32, f32, f32) {
+        let p = self.world_to_screen((r.0, r.1));
+        (p.0, p.1, r.2 * self.zoom, r.3 * self.zoom)
+    }
 
+    /// the part of the world thats on screen
+    /// rect: (x, y, w, h)
+    pub fn viewport(&self) -> (f32, f32, f32, f32) {
+        let p = self.screen_to_world((0.0, 0.0));
         (
-            view_offset.0 + self.resolution.0 / 2.0,
-            view_offset.1 - (self.zoom + self.resolution.1 / 2.0),
+            p.0,
+            p.1,
+            self.resolution.0 / self.zoom,
+            self.resolution.1 / self.zoom,
         )
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Camera;
+
+    /// equal within f32 precision of the largest value (in screen coords) that went into them
+    fn close(camera: Camera, a: (f32, f32), b: (f32, f32)) -> bool {
+        let largest = [a.0, a.1, b.0, b.1, camera.centre.0, camera.centre.1]
+            .iter()
+            .fold(camera.resolution.0.max(camera.resolution.1), |m, x| {
+                m.max(x.abs() * camera.zoom.max(1.0))
+            });
+        let tolerance = 1e-5 * (1.0 + largest);
+
+        (a.0 - b.0).abs() <= tolerance && (a.1 - b.1).abs() <= tolerance
+    }
 
-    // TODO: this is broken currently
-    /// rect `r`: (x, y, w, h)
-    pub fn contains(&self, r: (f32, f32, f32, f32)) -> bool {
-        (self.centre.0 >= r.0
-            && self.centre.0 <= (r.0 + r.2) * self.resolution.0 as f32 * self.zoom)
-            && (self.centre.1 >= r.1
-                && self.centre.1 <= (r.1 + r.3) * self.resolution.1 as f32 * self.zoom)
-            || true
+    /// a spread of cameras with different centres, zooms and resolutions
+    fn cameras() -> impl Iterator<Item = Camera> {
+        // simple lcg so the cases are the same every run
+        let mut seed = 0x2545f491u32;
+        let mut next = move || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 8) as f32 / (1 << 24) as f32
+        };
+
+        (0..200).map(move |_| Camera {
+            centre: ((next() - 0.5) * 1e4, (next() - 0.5) * 1e4),
+            zoom: Camera::MIN_ZOOM + next() * 10.0,
+            resolution: (1.0 + next() * 3000.0, 1.0 + next() * 3000.0),
+        })
+    }
+
+    #[test]
+    fn round_trips() {
+        for camera in cameras() {
+            for p in [(0.0, 0.0), (123.4, -56.7), (-9000.0, 4000.0)] {
+                assert!(close(
+                    camera,
+                    camera.screen_to_world(camera.world_to_screen(p)),
+                    p
+                ));
+                assert!(close(
+                    camera,
+                    camera.world_to_screen(camera.screen_to_world(p)),
+                    p
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn centre_is_middle_of_screen() {
+        for camera in cameras() {
+            assert!(close(
+                camera,
+                camera.world_to_screen(camera.centre),
+                (camera.resolution.0 / 2.0, camera.resolution.1 / 2.0)
+            ));
+        }
+    }
+
+    #[test]
+    fn zoom_keeps_anchor_in_place() {
+        for mut camera in cameras() {
+            let anchor = (camera.resolution.0 / 3.0, camera.resolution.1 * 0.8);
+            let before = camera.screen_to_world(anchor);
+
+            camera.add_zoom_at(0.5, anchor);
+            assert!(close(camera, camera.screen_to_world(anchor), before));
+            camera.add_zoom_at(-100.0, anchor);
+            assert!(close(camera, camera.screen_to_world(anchor), before));
+            assert_eq!(camera.zoom, Camera::MIN_ZOOM);
+        }
+    }
+
+    #[test]
+    fn viewport_covers_screen() {
+        for camera in cameras() {
+            let v = camera.viewport();
+            assert!(close(
+                camera,
+                camera.world_to_screen((v.0, v.1)),
+                (0.0, 0.0)
+            ));
+            assert!(close(
+                camera,
+                camera.world_to_screen((v.0 + v.2, v.1 + v.3)),
+                camera.resolution
+            ));
+
+            let r = camera.rect_to_screen(v);
+            assert!(close(camera, (r.2, r.3), camera.resolution));
+        }
     }
 }
@@ -0,0 +1,93 @@
+use std::error::Error;
+
+/// images on the clipboard as png, which [`cli_clipboard`] only does text for
+/// only wayland and x11 are supported
+#[derive(Default)]
+pub struct ImageClipboard {
+    /// x11 only has the clipboard for as long as whatever copied is around to hand it out
+    #[cfg(all(
+        unix,
+        not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+    ))]
+    x11: Option<x11_clipboard::Clipboard>,
+}
+
+const PNG: &str = "image/png";
+
+#[cfg(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+))]
+impl ImageClipboard {
+    #[inline]
+    fn wayland() -> bool {
+        std::env::var_os("WAYLAND_DISPLAY").is_some()
+    }
+
+    fn x11(&mut self) -> Result<&x11_clipboard::Clipboard, Box<dyn Error>> {
+        if self.x11.is_none() {
+            self.x11 = Some(x11_clipboard::Clipboard::new()?);
+        }
+        Ok(self.x11.as_ref().unwrap())
+    }
+
+    /// the png image on the clipboard
+    pub fn get(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        use std::io::Read;
+        use wl_clipboard_rs::paste::{self, ClipboardType, MimeType, Seat};
+
+        let bytes = if Self::wayland() {
+            let (mut pipe, _) = paste::get_contents(
+                ClipboardType::Regular,
+                Seat::Unspecified,
+                MimeType::Specific(PNG),
+            )?;
+            let mut bytes = Vec::new();
+            pipe.read_to_end(&mut bytes)?;
+            bytes
+        } else {
+            let x11 = self.x11()?;
+            x11.load(
+                x11.getter.atoms.clipboard,
+                x11.getter.get_atom(PNG)?,
+                x11.getter.atoms.property,
+                std::time::Duration::from_secs(1),
+            )?
+        };
+
+        if bytes.is_empty() {
+            return Err("theres no image on the clipboard".into());
+        }
+        Ok(bytes)
+    }
+
+    /// puts the `png` image on the clipboard
+    pub fn set(&mut self, png: Vec<u8>) -> Result<(), Box<dyn Error>> {
+        use wl_clipboard_rs::copy::{MimeType, Options, Source};
+
+        if Self::wayland() {
+            Options::new().copy(
+                Source::Bytes(png.into_boxed_slice()),
+                MimeType::Specific(PNG.to_owned()),
+            )?;
+        } else {
+            let x11 = self.x11()?;
+            x11.store(x11.setter.atoms.clipboard, x11.setter.get_atom(PNG)?, png)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(all(
+    unix,
+    not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))
+)))]
+impl ImageClipboard {
+    pub fn get(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        Err(format!("copying {PNG} isnt supported on this platform").into())
+    }
+
+    pub fn set(&mut self, _png: Vec<u8>) -> Result<(), Box<dyn Error>> {
+        Err(format!("copying {PNG} isnt supported on this platform").into())
+    }
+}
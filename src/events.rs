@@ -0,0 +1,210 @@
+use std::path::PathBuf;
+
+use ggez::event::winit_event::{ElementState, Event, KeyboardInput, MouseScrollDelta, WindowEvent};
+use ggez::event::{ErrorOrigin, EventHandler};
+use ggez::input::keyboard::{KeyInput, KeyMods};
+use ggez::winit::{dpi, event_loop::ControlFlow, event_loop::EventLoop};
+use ggez::{Context, GameError, GameResult};
+
+/// what the window does with files dropped onto it
+pub trait DropHandler {
+    /// `paths` being every file dropped at once
+    fn files_dropped(&mut self, ctx: &mut Context, paths: Vec<PathBuf>) -> GameResult;
+}
+
+/// the same as [`ggez::event::run`] (without gamepads) but also passing on dropped files,
+/// which ggez ignores
+pub fn run<S>(mut ctx: Context, event_loop: EventLoop<()>, mut state: S) -> !
+where
+    S: EventHandler + DropHandler + 'static,
+{
+    // files dropped together arrive one event at a time within the same frame
+    let mut dropped = Vec::new();
+
+    event_loop.run(move |mut event, _, control_flow| {
+        let ctx = &mut ctx;
+        let state = &mut state;
+
+        if ctx.quit_requested {
+            let res = state.quit_event(ctx);
+            ctx.quit_requested = false;
+            if let Ok(false) = res {
+                ctx.continuing = false;
+            } else if failed(ctx, res, state, control_flow, ErrorOrigin::QuitEvent) {
+                return;
+            }
+        }
+        if !ctx.continuing {
+            *control_flow = ControlFlow::Exit;
+            return;
+        }
+
+        *control_flow = ControlFlow::Poll;
+
+        ggez::event::process_event(ctx, &mut event);
+        let (res, origin) = match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::DroppedFile(path) => {
+                    dropped.push(path);
+                    return;
+                }
+                WindowEvent::Resized(size) => (
+                    state.resize_event(ctx, size.width as f32, size.height as f32),
+                    ErrorOrigin::ResizeEvent,
+                ),
+                WindowEvent::CloseRequested => {
+                    let res = state.quit_event(ctx);
+                    if let Ok(false) = res {
+                        ctx.continuing = false;
+                    }
+                    (res.map(|_| ()), ErrorOrigin::QuitEvent)
+                }
+                WindowEvent::Focused(gained) => {
+                    (state.focus_event(ctx, gained), ErrorOrigin::FocusEvent)
+                }
+                WindowEvent::ReceivedCharacter(ch) => {
+                    (state.text_input_event(ctx, ch), ErrorOrigin::TextInputEvent)
+                }
+                WindowEvent::ModifiersChanged(mods) => {
+                    ctx.keyboard.set_modifiers(KeyMods::from(mods));
+                    return;
+                }
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: key_state,
+                            virtual_keycode: keycode,
+                            scancode,
+                            ..
+                        },
+                    ..
+                } => {
+                    let input = KeyInput {
+                        scancode,
+                        keycode,
+                        mods: ctx.keyboard.active_mods(),
+                    };
+                    match key_state {
+                        ElementState::Pressed => {
+                            let repeated = ctx.keyboard.is_key_repeated();
+                            (
+                                state.key_down_event(ctx, input, repeated),
+                                ErrorOrigin::KeyDownEvent,
+                            )
+                        }
+                        ElementState::Released => {
+                            (state.key_up_event(ctx, input), ErrorOrigin::KeyUpEvent)
+                        }
+                    }
+                }
+                WindowEvent::MouseWheel { delta, .. } => {
+                    let (x, y) = match delta {
+                        MouseScrollDelta::LineDelta(x, y) => (x, y),
+                        MouseScrollDelta::PixelDelta(pos) => {
+                            let scale_factor = ctx.gfx.window().scale_factor();
+                            let dpi::LogicalPosition { x, y } = pos.to_logical::<f32>(scale_factor);
+                            (x, y)
+                        }
+                    };
+                    (
+                        state.mouse_wheel_event(ctx, x, y),
+                        ErrorOrigin::MouseWheelEvent,
+                    )
+                }
+                WindowEvent::MouseInput {
+                    state: button_state,
+                    button,
+                    ..
+                } => {
+                    let position = ctx.mouse.position();
+                    match button_state {
+                        ElementState::Pressed => (
+                            state.mouse_button_down_event(ctx, button, position.x, position.y),
+                            ErrorOrigin::MouseButtonDownEvent,
+                        ),
+                        ElementState::Released => (
+                            state.mouse_button_up_event(ctx, button, position.x, position.y),
+                            ErrorOrigin::MouseButtonUpEvent,
+                        ),
+                    }
+                }
+                WindowEvent::CursorMoved { .. } => {
+                    let (position, delta) = (ctx.mouse.position(), ctx.mouse.last_delta());
+                    (
+                        state.mouse_motion_event(ctx, position.x, position.y, delta.x, delta.y),
+                        ErrorOrigin::MouseMotionEvent,
+                    )
+                }
+                WindowEvent::CursorEntered { .. } => (
+                    state.mouse_enter_or_leave(ctx, true),
+                    ErrorOrigin::MouseEnterOrLeave,
+                ),
+                WindowEvent::CursorLeft { .. } => (
+                    state.mouse_enter_or_leave(ctx, false),
+                    ErrorOrigin::MouseEnterOrLeave,
+                ),
+                _ => return,
+            },
+            Event::MainEventsCleared => {
+                ctx.time.tick();
+
+                if !dropped.is_empty() {
+                    let res = state.files_dropped(ctx, std::mem::take(&mut dropped));
+                    if failed(ctx, res, state, control_flow, ErrorOrigin::Update) {
+                        return;
+                    }
+                }
+
+                let res = state.update(ctx);
+                if failed(ctx, res, state, control_flow, ErrorOrigin::Update) {
+                    return;
+                }
+
+                if let Err(e) = ctx.gfx.begin_frame() {
+                    println!("Error on GraphicsContext::begin_frame(): {e:?}");
+                    *control_flow = ControlFlow::Exit;
+                }
+                if let Err(e) = state.draw(ctx) {
+                    println!("Error on EventHandler::draw(): {e:?}");
+                    if state.on_error(ctx, ErrorOrigin::Draw, e) {
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                }
+                if let Err(e) = ctx.gfx.end_frame() {
+                    println!("Error on GraphicsContext::end_frame(): {e:?}");
+                    *control_flow = ControlFlow::Exit;
+                }
+
+                // the deltas are worked out cumulatively each frame
+                ctx.mouse.reset_delta();
+                ctx.keyboard.save_keyboard_state();
+                ctx.mouse.save_mouse_state();
+                return;
+            }
+            _ => return,
+        };
+
+        failed(ctx, res, state, control_flow, origin);
+    })
+}
+
+/// reports an error from the handler, returns whether the loop is stopping because of it
+fn failed<T, S: EventHandler>(
+    ctx: &mut Context,
+    res: Result<T, GameError>,
+    state: &mut S,
+    control_flow: &mut ControlFlow,
+    origin: ErrorOrigin,
+) -> bool {
+    let Err(e) = res else {
+        return false;
+    };
+
+    println!("Error on EventHandler {origin:?}: {e:?}");
+    if state.on_error(ctx, origin, e) {
+        *control_flow = ControlFlow::Exit;
+        return true;
+    }
+    false
+}
@@ -1,14 +1,19 @@
-use board::board::Selectable;
+use board::board::{Copied, Depth, Selectable};
+use board::layer::{Layer, Layers};
+use board::shape::Tool;
 use ggez::event::{self, EventHandler};
 use ggez::graphics::{self, Color};
 use ggez::input::keyboard::{KeyCode, KeyMods};
 use ggez::{Context, ContextBuilder, GameResult};
+use std::time::Duration;
 
 use cli_clipboard::{ClipboardContext, ClipboardProvider};
 use serde::{Deserialize, Serialize};
 
 mod board;
 mod camera;
+mod clipboard;
+mod events;
 mod notifications;
 
 pub(crate) const LIGHT: Color = Color::new(237. / 255., 230. / 255., 230. / 255., 1.0);
@@ -18,7 +23,34 @@ const NOTIFICATION_TIME: f32 = std::f32::consts::FRAC_PI_2;
 
 fn main() {
     let default_store_path = "test_store".to_owned();
-    let args = std::env::args().collect::<Vec<String>>();
+    let mut args = std::env::args().collect::<Vec<String>>();
+
+    // board --import <archive> [store path]
+    if args.get(1).is_some_and(|a| a == "--import") {
+        let Some(archive) = args.get(2).cloned() else {
+            println!("usage: board --import <archive> [store path]");
+            return;
+        };
+        let store_path = args.get(3).cloned().unwrap_or_else(|| {
+            std::path::Path::new(&archive)
+                .file_stem()
+                .map_or(default_store_path.clone(), |s| {
+                    s.to_string_lossy().into_owned()
+                })
+        });
+
+        match board::archive::import(archive.as_ref(), store_path.as_ref()) {
+            Ok(manifest) => println!(
+                "imported {archive} into {store_path} with {} images",
+                manifest.images.len()
+            ),
+            Err(e) => {
+                println!("couldnt import {archive}: {e}");
+                return;
+            }
+        }
+        args = vec![args.swap_remove(0), store_path];
+    }
     let store_path = args.get(1).unwrap_or(&default_store_path);
 
     let (mut ctx, event_loop) = ContextBuilder::new(&format!("board - {store_path}"), "")
@@ -30,9 +62,10 @@ fn main() {
 
     let app = BoardApp::new(store_path, &mut ctx).unwrap();
 
-    event::run(ctx, event_loop, app);
+    events::run(ctx, event_loop, app);
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Deserialize, Serialize, Default)]
 enum Mode {
     #[default]
@@ -41,12 +74,30 @@ enum Mode {
 }
 
 // TODO: probably move this to "store.rs"
-#[derive(Debug, Deserialize, Serialize, Default)]
+/// missing fields (like from older versions) are defaulted instead of failing
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
 struct BoardAppState {
     mode: Mode,
     // TODO: use bitfields
     draw_bounds: bool,
     draw_selection_info: bool,
+    /// how many older versions of the store are kept
+    keep_backups: usize,
+    /// whether animated images keep playing while theyre off screen
+    animate_offscreen: bool,
+}
+
+impl Default for BoardAppState {
+    fn default() -> Self {
+        Self {
+            mode: Mode::default(),
+            draw_bounds: false,
+            draw_selection_info: false,
+            keep_backups: 10,
+            animate_offscreen: false,
+        }
+    }
 }
 
 struct BoardApp {
@@ -54,8 +105,18 @@ struct BoardApp {
     store_path: String,
 
     clipboard: ClipboardContext,
+    image_clipboard: clipboard::ImageClipboard,
     notifications: notifications::Notifications<notifications::MyNotification>,
     state: BoardAppState,
+
+    /// when and which item was last clicked, for double clicks
+    last_click: Option<(std::time::Instant, usize)>,
+    /// when autosaving last failed, so it isnt retried every frame
+    autosave_failed: Option<std::time::Instant>,
+    /// when the store was last checked for changes made elsewhere
+    last_sync: std::time::Instant,
+    /// whether quitting already failed to save, quitting again doesnt try
+    quit_unsaved: bool,
 }
 
 impl BoardAppState {
@@ -88,7 +149,7 @@ impl BoardAppState {
         }
 
         let mut cache = std::fs::OpenOptions::new().write(true).open(&path)?;
-        write!(cache, "{}\n", serde_json::to_string_pretty(self)?)?;
+        writeln!(cache, "{}", serde_json::to_string_pretty(self)?)?;
 
         Ok(())
     }
@@ -96,14 +157,22 @@ impl BoardAppState {
 
 impl BoardApp {
     fn new(store_path: &str, ctx: &mut Context) -> GameResult<Self> {
+        let state = BoardAppState::new(store_path);
+
         Ok(Self {
-            board: board::board::Board::create(store_path, ctx).expect("couldnt create board"),
+            board: board::board::Board::create(store_path, state.keep_backups, ctx)
+                .expect("couldnt create board"),
             store_path: store_path.to_owned(),
 
             clipboard: ClipboardContext::new().expect("couldnt create clipboard"),
+            image_clipboard: clipboard::ImageClipboard::default(),
             notifications: notifications::Notifications::with_colour(DARK),
 
-            state: BoardAppState::new(store_path),
+            state,
+            last_click: None,
+            autosave_failed: None,
+            last_sync: std::time::Instant::now(),
+            quit_unsaved: false,
         }
         .with_proper_colours())
     }
@@ -139,7 +208,226 @@ impl BoardApp {
         self
     }
 
+    fn confirm_choice(&mut self, pos: (f32, f32)) {
+        match self.board.confirm_choice(pos) {
+            Ok(true) => self.notifications.add(notifications::MyNotification::new(
+                format!("added {}", self.board.get(self.board.len() - 1).unwrap()),
+                NOTIFICATION_TIME,
+            )),
+            Ok(false) => (),
+            Err(e) => println!("error while picking a choice: {e}"),
+        }
+    }
+
+    fn undo(&mut self) {
+        self.notifications.add(notifications::MyNotification::new(
+            if self.board.undo() {
+                "undone"
+            } else {
+                "nothing to undo"
+            }
+            .to_owned(),
+            NOTIFICATION_TIME,
+        ))
+    }
+
+    fn redo(&mut self) {
+        self.notifications.add(notifications::MyNotification::new(
+            if self.board.redo() {
+                "redone"
+            } else {
+                "nothing to redo"
+            }
+            .to_owned(),
+            NOTIFICATION_TIME,
+        ))
+    }
+
+    /// changes the style of the selected text items with `text` and shapes with `shape`
+    fn restyle(
+        &mut self,
+        what: &str,
+        text: impl Fn(&mut board::style::TextStyle),
+        shape: impl Fn(&mut board::style::ShapeStyle),
+    ) {
+        let changed = self.board.restyle_selection(text, shape);
+        if changed != 0 {
+            self.notifications.add(notifications::MyNotification::new(
+                format!("changed the {what} of {changed} items"),
+                NOTIFICATION_TIME,
+            ));
+        }
+    }
+
+    fn reorder(&mut self, to: Depth) {
+        if self.board.reorder_selection(to) {
+            self.notifications.add(notifications::MyNotification::new(
+                format!("moved the selection {}", format!("{to:?}").to_lowercase()),
+                NOTIFICATION_TIME,
+            ));
+        }
+    }
+
+    /// changes the layers with `f` which returns the layer that changed
+    fn change_layers(&mut self, what: &str, f: impl FnOnce(&mut Layers) -> &Layer) {
+        let layer = self.board.change_layers(|l| f(l).to_string());
+        self.notifications.add(notifications::MyNotification::new(
+            format!("{what}: {layer}"),
+            NOTIFICATION_TIME,
+        ));
+    }
+
+    fn set_tool(&mut self, tool: Tool) {
+        self.board.set_tool(tool);
+        self.notifications.add(notifications::MyNotification::new(
+            format!("{tool} tool"),
+            NOTIFICATION_TIME,
+        ));
+    }
+
+    /// starts editing the selected text item if it was clicked twice in a row
+    fn double_click(&mut self) {
+        const DOUBLE_CLICK_TIME: std::time::Duration = std::time::Duration::from_millis(400);
+
+        let Some(Selectable::Item(i)) = self.board.selected() else {
+            return;
+        };
+        let now = std::time::Instant::now();
+
+        match self.last_click {
+            Some((at, last)) if last == i && now - at < DOUBLE_CLICK_TIME => {
+                self.last_click = None;
+                self.board.start_editing(i);
+            }
+            _ => self.last_click = Some((now, i)),
+        }
+    }
+
+    /// adds the image on the clipboard
+    fn paste_image(&mut self, ctx: &Context) {
+        match self
+            .image_clipboard
+            .get()
+            .and_then(|bytes| self.board.add_image_data(&bytes, ctx))
+        {
+            Ok(()) => self.notifications.add(notifications::MyNotification::new(
+                "added the image from the clipboard".to_owned(),
+                NOTIFICATION_TIME,
+            )),
+            Err(e) => self.notifications.add(notifications::MyNotification::new(
+                format!("couldnt paste an image: {e}"),
+                NOTIFICATION_TIME,
+            )),
+        }
+    }
+
+    /// puts the selected item on the clipboard
+    fn copy(&mut self) {
+        let copied = self.board.copy_selection().and_then(|copied| match copied {
+            Some(Copied::Text(text)) => self.clipboard.set_contents(text).map(|_| true),
+            Some(Copied::Image(png)) => self.image_clipboard.set(png).map(|_| true),
+            None => Ok(false),
+        });
+
+        match copied {
+            Ok(false) => (),
+            Ok(true) => self.notifications.add(notifications::MyNotification::new(
+                "copied".to_owned(),
+                NOTIFICATION_TIME,
+            )),
+            Err(e) => self.notifications.add(notifications::MyNotification::new(
+                format!("couldnt copy: {e}"),
+                NOTIFICATION_TIME,
+            )),
+        }
+    }
+
+    /// every key goes to the text being edited
+    fn edit_key(&mut self, input: ggez::input::keyboard::KeyInput) {
+        let ctrl = input.mods.contains(KeyMods::CTRL);
+
+        match input.keycode {
+            Some(KeyCode::Back) => self.board.edit_text(|e| e.backspace()),
+            Some(KeyCode::Delete) => self.board.edit_text(|e| e.delete()),
+            Some(KeyCode::Left) => self.board.edit_text(|e| e.left()),
+            Some(KeyCode::Right) => self.board.edit_text(|e| e.right()),
+            Some(KeyCode::Up) => self.board.edit_text(|e| e.up()),
+            Some(KeyCode::Down) => self.board.edit_text(|e| e.down()),
+            Some(KeyCode::Home) => self.board.edit_text(|e| e.home()),
+            Some(KeyCode::End) => self.board.edit_text(|e| e.end()),
+            Some(KeyCode::Return | KeyCode::NumpadEnter) => {
+                self.board.edit_text(|e| e.insert("\n"))
+            }
+            Some(KeyCode::Escape) => self.board.finish_editing(),
+
+            Some(KeyCode::C) if ctrl => {
+                let text = self.board.editor().unwrap().text().to_owned();
+                if let Err(e) = self.clipboard.set_contents(text) {
+                    println!("couldnt copy the text: {e}");
+                }
+            }
+            Some(KeyCode::V) if ctrl => {
+                if let Ok(s) = self.clipboard.get_contents() {
+                    self.board.edit_text(|e| e.insert(&s));
+                }
+            }
+
+            _ => (),
+        }
+    }
+
+    /// saves once nothing has changed for a bit, or changes have gone unsaved for too long
+    /// failing saves are retried after a while
+    fn autosave(&mut self) {
+        const IDLE: Duration = Duration::from_secs(2);
+        const AT_MOST: Duration = Duration::from_secs(30);
+
+        let Some((first, last)) = self.board.unsaved() else {
+            return;
+        };
+        let now = std::time::Instant::now();
+        let due = now - last >= IDLE || now - first >= AT_MOST;
+        let retrying = self.autosave_failed.is_some_and(|at| now - at < AT_MOST);
+        if !due || retrying || self.board.editor().is_some() {
+            return;
+        }
+
+        self.autosave_failed = None;
+        if let Err(e) = self.save() {
+            self.autosave_failed = Some(now);
+            self.notifications.add(notifications::MyNotification::new(
+                format!("couldnt autosave: {e}"),
+                NOTIFICATION_TIME,
+            ));
+        }
+    }
+
+    /// reloads the board if something else changed the store, checking every second
+    fn sync(&mut self, ctx: &Context) {
+        if self.last_sync.elapsed() < Duration::from_secs(1) {
+            return;
+        }
+        self.last_sync = std::time::Instant::now();
+
+        match self.board.sync(ctx) {
+            Ok(false) => (),
+            Ok(true) => self.notifications.add(notifications::MyNotification::new(
+                if self.board.unsaved().is_some() {
+                    "merged in changes made elsewhere".to_owned()
+                } else {
+                    "reloaded the board after it changed elsewhere".to_owned()
+                },
+                NOTIFICATION_TIME,
+            )),
+            Err(e) => self.notifications.add(notifications::MyNotification::new(
+                format!("couldnt reload the board: {e}"),
+                NOTIFICATION_TIME,
+            )),
+        }
+    }
+
     fn save(&mut self) -> std::io::Result<()> {
+        self.board.finish_editing();
         self.board.save()?;
         self.state.save(&self.store_path)?;
         Ok(())
@@ -149,6 +437,17 @@ impl BoardApp {
 impl EventHandler for BoardApp {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
         self.board.manage(ctx);
+        self.board
+            .animate(ctx.time.delta(), self.state.animate_offscreen);
+        self.board.refresh_resolutions(ctx);
+        for message in self.board.poll_downloads(ctx) {
+            self.notifications.add(notifications::MyNotification::new(
+                message,
+                NOTIFICATION_TIME,
+            ));
+        }
+        self.sync(ctx);
+        self.autosave();
         self.notifications
             .update_all(ctx.time.delta().as_secs_f32());
 
@@ -165,6 +464,8 @@ impl EventHandler for BoardApp {
         if self.state.draw_selection_info {
             self.board.draw_selection_info(&mut canvas, ctx)
         }
+        self.board.draw_choices(&mut canvas, ctx);
+        self.board.draw_layers(&mut canvas);
 
         self.notifications.display_all(&mut canvas);
 
@@ -177,33 +478,67 @@ impl EventHandler for BoardApp {
         input: ggez::input::keyboard::KeyInput,
         repeated: bool,
     ) -> Result<(), ggez::GameError> {
+        if self.board.editor().is_some() {
+            self.edit_key(input);
+            return Ok(());
+        }
+
         // dont care for holding anything down
         if repeated || input.keycode.is_none() {
             return Ok(());
         }
 
+        if self.board.choosing() {
+            match input.keycode.unwrap() {
+                KeyCode::Left => self.board.move_choice(-1),
+                KeyCode::Right => self.board.move_choice(1),
+                KeyCode::Up => self.board.move_choice_page(-1),
+                KeyCode::Down => self.board.move_choice_page(1),
+                KeyCode::Return | KeyCode::NumpadEnter => {
+                    self.confirm_choice((ctx.mouse.position().x, ctx.mouse.position().y))
+                }
+                KeyCode::Escape => {
+                    if let Err(e) = self.board.cancel_choices() {
+                        println!("error while cancelling choices: {e}");
+                    }
+                }
+                _ => (),
+            }
+
+            return Ok(());
+        }
+
         match input.keycode.unwrap() {
             KeyCode::A => {
-                let Ok(s) = self.clipboard.get_contents() else {
-                    return Ok(());
+                // screenshots and such are on the clipboard without any text
+                let s = match self.clipboard.get_contents() {
+                    Ok(s) if !s.is_empty() => s,
+                    _ => {
+                        self.paste_image(ctx);
+                        return Ok(());
+                    }
                 };
                 let mut success = true;
 
                 let kind = board::board::ImageType::type_from_argument(&s);
+                let text = kind.argument().is_empty() || input.mods.contains(KeyMods::SHIFT);
+                let web = !text && matches!(kind, board::board::ImageType::Web(_));
 
-                if kind.argument().is_empty() || input.mods.contains(KeyMods::SHIFT) {
+                if text {
                     self.board.add_text(s);
-                } else {
-                    if let Err(e) = self.board.add_image(kind, ctx) {
-                        // TODO: possibly notify the user?
-                        println!("Error: {e}");
-                        success = false;
-                    }
+                } else if let Err(e) = self.board.add_image(kind, ctx) {
+                    // TODO: possibly notify the user?
+                    println!("Error: {e}");
+                    success = false;
                 }
 
                 if success {
                     self.notifications.add(notifications::MyNotification::new(
-                        format!("added {}", self.board.get(self.board.len() - 1).unwrap()),
+                        if web {
+                            "loading the link".to_owned()
+                        } else {
+                            format!("added {}", self.board.get(self.board.len() - 1).unwrap())
+                        },
                         NOTIFICATION_TIME,
                     ));
                 }
@@ -214,25 +549,188 @@ impl EventHandler for BoardApp {
                     "board was saved".to_owned(),
                     NOTIFICATION_TIME,
                 )),
-                Err(e) => println!("error while saving: {e}"),
+                Err(e) => {
+                    println!("error while saving: {e}");
+                    self.notifications.add(notifications::MyNotification::new(
+                        format!("couldnt save: {e}"),
+                        NOTIFICATION_TIME,
+                    ));
+                }
             },
 
-            KeyCode::X => {
-                if let Some(Selectable::Item(i)) = self.board.selected() {
+            KeyCode::E if input.mods.contains(KeyMods::CTRL) => {
+                let to = format!("{}.zip", self.store_path.trim_end_matches('/'));
+                match self
+                    .save()
+                    .map_err(Box::from)
+                    .and_then(|_| self.board.export(to.as_ref()))
+                {
+                    Ok(manifest) => self.notifications.add(notifications::MyNotification::new(
+                        format!(
+                            "exported the board to {to} with {} images",
+                            manifest.images.len()
+                        ),
+                        NOTIFICATION_TIME,
+                    )),
+                    Err(e) => self.notifications.add(notifications::MyNotification::new(
+                        format!("couldnt export the board: {e}"),
+                        NOTIFICATION_TIME,
+                    )),
+                }
+            }
+
+            KeyCode::X => match *self.board.selection() {
+                [] => (),
+                [i] => {
                     self.notifications.add(notifications::MyNotification::new(
                         format!("removed item {i} ({})", self.board.get(i).unwrap()),
                         NOTIFICATION_TIME,
                     ));
+                    self.board.remove_selection();
+                }
+                _ => {
+                    let removed = self.board.remove_selection();
+                    self.notifications.add(notifications::MyNotification::new(
+                        format!("removed {removed} items"),
+                        NOTIFICATION_TIME,
+                    ));
+                }
+            },
 
-                    self.board.remove(i)?
+            KeyCode::Z if input.mods.contains(KeyMods::CTRL) => {
+                if input.mods.contains(KeyMods::SHIFT) {
+                    self.redo()
+                } else {
+                    self.undo()
+                }
+            }
+            KeyCode::Y if input.mods.contains(KeyMods::CTRL) => self.redo(),
+
+            KeyCode::F => self.restyle("font", |s| s.font = s.font.next(), |_| ()),
+            KeyCode::C if input.mods.contains(KeyMods::CTRL) => self.copy(),
+            KeyCode::C => self.restyle("colour", |s| s.next_colour(), |s| s.next_stroke()),
+            KeyCode::J => self.restyle("alignment", |s| s.align = s.align.next(), |_| ()),
+            KeyCode::W => self.restyle("width", |s| s.next_wrap(), |s| s.next_width()),
+            KeyCode::G if input.mods.contains(KeyMods::CTRL) => {
+                match self.board.collect_garbage() {
+                    Ok(removed) => self.notifications.add(notifications::MyNotification::new(
+                        format!("removed {removed} unused cached images"),
+                        NOTIFICATION_TIME,
+                    )),
+                    Err(e) => println!("error while removing unused cached images: {e}"),
+                }
+            }
+            KeyCode::G => self.restyle("fill", |_| (), |s| s.next_fill()),
+
+            KeyCode::K if self.board.connect_selection() => {
+                self.notifications.add(notifications::MyNotification::new(
+                    "connected the items".to_owned(),
+                    NOTIFICATION_TIME,
+                ))
+            }
+            KeyCode::O => {
+                let changed = self.board.reroute_selection();
+                if changed != 0 {
+                    self.notifications.add(notifications::MyNotification::new(
+                        format!("changed the routing of {changed} connectors"),
+                        NOTIFICATION_TIME,
+                    ));
+                }
+            }
+
+            KeyCode::R if input.mods.contains(KeyMods::CTRL) => {
+                let retried = self.board.retry_selection(ctx);
+                if retried != 0 {
+                    self.notifications.add(notifications::MyNotification::new(
+                        format!("retrying {retried} images"),
+                        NOTIFICATION_TIME,
+                    ));
+                }
+            }
+            KeyCode::P if input.mods.contains(KeyMods::SHIFT) => {
+                self.state.animate_offscreen = !self.state.animate_offscreen;
+                self.notifications.add(notifications::MyNotification::new(
+                    if self.state.animate_offscreen {
+                        "animations play off screen"
+                    } else {
+                        "animations pause off screen"
+                    }
+                    .to_owned(),
+                    NOTIFICATION_TIME,
+                ));
+            }
+            KeyCode::P if input.mods.contains(KeyMods::CTRL) => {
+                let Ok(path) = self.clipboard.get_contents() else {
+                    return Ok(());
+                };
+                match self.board.relink_selection(path.trim(), ctx) {
+                    Ok(0) => (),
+                    Ok(relinked) => self.notifications.add(notifications::MyNotification::new(
+                        format!("relinked {relinked} images to {}", path.trim()),
+                        NOTIFICATION_TIME,
+                    )),
+                    Err(e) => self.notifications.add(notifications::MyNotification::new(
+                        format!("couldnt relink to {}: {e}", path.trim()),
+                        NOTIFICATION_TIME,
+                    )),
+                }
+            }
+
+            KeyCode::P => {
+                let toggled = self.board.toggle_playing_selection();
+                if toggled != 0 {
+                    self.notifications.add(notifications::MyNotification::new(
+                        format!("played/paused {toggled} animations"),
+                        NOTIFICATION_TIME,
+                    ));
                 }
             }
 
-            KeyCode::Tab => {
-                if input.mods.is_empty() {
-                    self.switch_colours()
+            KeyCode::PageUp => self.reorder(Depth::Forward),
+            KeyCode::PageDown => self.reorder(Depth::Backward),
+            KeyCode::Home => self.reorder(Depth::Front),
+            KeyCode::End => self.reorder(Depth::Back),
+
+            KeyCode::N if input.mods.contains(KeyMods::CTRL) => {
+                if let Ok(name) = self.clipboard.get_contents() {
+                    self.change_layers("renamed layer", |l| l.rename(name));
                 }
             }
+            KeyCode::N => self.change_layers("new layer", |l| l.add()),
+            KeyCode::LBracket if input.mods.contains(KeyMods::CTRL) => {
+                self.change_layers("moved layer", |l| l.move_current(-1))
+            }
+            KeyCode::RBracket if input.mods.contains(KeyMods::CTRL) => {
+                self.change_layers("moved layer", |l| l.move_current(1))
+            }
+            KeyCode::LBracket => self.change_layers("layer", |l| l.select(-1)),
+            KeyCode::RBracket => self.change_layers("layer", |l| l.select(1)),
+            KeyCode::H if input.mods.contains(KeyMods::CTRL) => {
+                self.change_layers("layer", |l| l.toggle_hidden())
+            }
+            KeyCode::L if input.mods.contains(KeyMods::CTRL) => {
+                self.change_layers("layer", |l| l.toggle_locked())
+            }
+            KeyCode::M if input.mods.contains(KeyMods::CTRL) => {
+                let moved = self.board.move_selection_to_layer();
+                if moved != 0 {
+                    self.notifications.add(notifications::MyNotification::new(
+                        format!("moved {moved} items to {}", self.board.layers().current()),
+                        NOTIFICATION_TIME,
+                    ));
+                }
+            }
+
+            KeyCode::M => self.restyle("markdown", |s| s.markdown = !s.markdown, |_| ()),
+
+            KeyCode::Key1 => self.set_tool(Tool::Select),
+            KeyCode::Key2 => self.set_tool(Tool::Rectangle),
+            KeyCode::Key3 => self.set_tool(Tool::Ellipse),
+            KeyCode::Key4 => self.set_tool(Tool::Line),
+            KeyCode::Key5 => self.set_tool(Tool::Arrow),
+            KeyCode::Key6 => self.set_tool(Tool::Pen),
+
+            KeyCode::Tab if input.mods.is_empty() => self.switch_colours(),
 
             KeyCode::Space => self.state.draw_bounds = !self.state.draw_bounds,
             KeyCode::D => self.state.draw_selection_info = !self.state.draw_selection_info,
@@ -241,9 +739,41 @@ impl EventHandler for BoardApp {
             KeyCode::H => self.notifications.add(notifications::MyNotification::new(
                 "
 Key, Mouse  Action
-A           Add item from clipboard
+A           Add item from clipboard (text, a link, a path or an image)
+            (web pages: Left/Right, Up/Down, SW to browse, Enter, L to pick)
+C-C         Copy the selected item (its text or image)
+Drop files  Add images (or text) where theyre dropped
 S           Save the board
-X           Delete the selected item
+C-G         Remove cached images nothing uses
+C-Escape    Cancel the image downloads
+C-R         Retry loading the selected broken images
+C-P         Relink the selected images to the file path in the clipboard
+P           Play/pause the selected animated images
+S-P         Keep animations playing off screen or not
+C-E         Export the board to a single archive (board --import to open it)
+S-L         Add/remove an item from the selection
+S-L (board) Select everything in a box
+X           Delete the selected items
+L L (text)  Edit the text, Escape or clicking elsewhere to stop
+F, C, J, W  Change the font, colour, alignment, wrapping of selected text
+M           Toggle **bold** and *italic* markdown of selected text
+1-6         Select, rectangle, ellipse, line, arrow, pen tool
+L (tool)    Draw with the tool
+            (arrows drawn from one item to another connect them)
+C, G, W     Change the stroke colour, fill, width of selected shapes
+K           Connect the two selected items
+O           Change the routing of selected connectors
+L L (conn.) Edit the connectors label
+PgUp, PgDn  Move the selection forward, backward in its layer
+Home, End   Move the selection to the front, back of its layer
+N           Add a layer above the current one
+[, ]        Select the layer below, above
+C-[, C-]    Move the current layer down, up
+C-H, C-L    Hide, lock the current layer
+C-M         Move the selection to the current layer
+C-N         Rename the current layer from the clipboard
+C-Z         Undo
+C-S-Z, C-Y  Redo
 E, R+L      Scale the selected item
 R, R        Scale the selected item
  , R+L / SW Zoom
@@ -256,6 +786,13 @@ H           This help menu :)
                 NOTIFICATION_TIME,
             )),
 
+            KeyCode::Escape if input.mods.contains(KeyMods::CTRL) => {
+                let cancelled = self.board.cancel_downloads();
+                self.notifications.add(notifications::MyNotification::new(
+                    format!("cancelled {cancelled} downloads"),
+                    NOTIFICATION_TIME,
+                ));
+            }
             KeyCode::Escape => ctx.request_quit(),
 
             _ => (),
@@ -271,14 +808,53 @@ H           This help menu :)
         x: f32,
         y: f32,
     ) -> Result<(), ggez::GameError> {
+        if self.board.choosing() {
+            if button == event::MouseButton::Left {
+                if let Some(i) = self.board.choice_at((x, y)) {
+                    self.board.highlight_choice(i);
+                    self.confirm_choice((x, y));
+                }
+            }
+
+            return Ok(());
+        }
+
         if self.board.selected().is_none()
             && (button == event::MouseButton::Left || button == event::MouseButton::Right)
         {
             self.board.set_last_press((x, y));
 
-            match self.board.select((x, y), ctx) {
-                Some(i) => self.board.set_selection(Selectable::Item(i)),
-                None => self.board.set_selection(Selectable::Board),
+            let shift = ctx.keyboard.active_mods().contains(KeyMods::SHIFT);
+            if button == event::MouseButton::Left
+                && !shift
+                && self.board.editor().is_none()
+                && self.board.start_drawing((x, y), ctx)
+            {
+                return Ok(());
+            }
+
+            let select = self.board.select((x, y), ctx);
+
+            if let Some(editing) = self.board.editor().map(|e| e.item()) {
+                // clicking anywhere else stops editing
+                if select != Some(editing) {
+                    self.board.finish_editing();
+                }
+                return Ok(());
+            }
+
+            match select {
+                Some(i) if shift => self.board.toggle_selection(i, ctx),
+                Some(i) => {
+                    self.board.set_selection(Selectable::Item(i), ctx);
+                    if button == event::MouseButton::Left {
+                        self.double_click();
+                    }
+                }
+                None if shift && button == event::MouseButton::Left => {
+                    self.board.set_selection(Selectable::Marquee, ctx)
+                }
+                None => self.board.set_selection(Selectable::Board, ctx),
             }
         }
 
@@ -291,6 +867,14 @@ H           This help menu :)
         _x: f32,
         y: f32,
     ) -> Result<(), ggez::GameError> {
+        if self.board.choosing() {
+            // horizontal scrolls dont turn the page (0.0.signum() is 1.0)
+            if y != 0.0 {
+                self.board.move_choice_page(-y.signum() as isize);
+            }
+            return Ok(());
+        }
+
         self.board.camera.mouse_wheel_event(_ctx, _x, y)
     }
 
@@ -300,13 +884,63 @@ H           This help menu :)
         width: f32,
         height: f32,
     ) -> Result<(), ggez::GameError> {
-        Ok(self.board.camera.resolution = (width, height))
+        self.board.camera.resolution = (width, height);
+        Ok(())
     }
 
+    fn text_input_event(&mut self, ctx: &mut Context, character: char) -> GameResult {
+        // shortcuts come through here too
+        if !character.is_control() && !ctx.keyboard.active_mods().contains(KeyMods::CTRL) {
+            self.board
+                .edit_text(|e| e.insert(character.encode_utf8(&mut [0; 4])));
+        }
+
+        Ok(())
+    }
+
+    /// saves before quitting, if that fails quitting is cancelled once so the error can be seen
     fn quit_event(&mut self, _ctx: &mut Context) -> Result<bool, ggez::GameError> {
-        self.save().expect("failed to save");
+        if self.quit_unsaved {
+            return Ok(false);
+        }
+
+        if let Err(e) = self.save() {
+            println!("couldnt save before quitting: {e}");
+            self.notifications.add(notifications::MyNotification::new(
+                format!("couldnt save: {e}\nquit again to quit without saving"),
+                NOTIFICATION_TIME * 2.0,
+            ));
+            self.quit_unsaved = true;
+            return Ok(true);
+        }
         println!("auto saved the board");
+        // nothing can be undone after this so removed items caches can go
+        self.board.clear_history();
 
         Ok(false)
     }
 }
+
+impl events::DropHandler for BoardApp {
+    /// adds the dropped files where the mouse is
+    fn files_dropped(&mut self, ctx: &mut Context, paths: Vec<std::path::PathBuf>) -> GameResult {
+        self.board.finish_editing();
+        let pos = (ctx.mouse.position().x, ctx.mouse.position().y);
+        let errors = self.board.add_dropped(&paths, pos, ctx);
+
+        let added = paths.len() - errors.len();
+        if added != 0 {
+            self.notifications.add(notifications::MyNotification::new(
+                format!("added {added} dropped items"),
+                NOTIFICATION_TIME,
+            ));
+        }
+        for e in errors {
+            println!("{e}");
+            self.notifications
+                .add(notifications::MyNotification::new(e, NOTIFICATION_TIME));
+        }
+
+        Ok(())
+    }
+}